@@ -34,6 +34,12 @@ impl AbsPath {
         }
     }
 
+    /// Trusts that a path recorded from a prior `AbsPath` is still absolute
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_recorded(path: PathBuf) -> Self {
+        Self(path)
+    }
+
     /// Tries to read the current path as a directory
     ///
     /// The properties of `read_dir` state that the resulting paths returned from `DirEntry`
@@ -68,6 +74,41 @@ impl AbsPath {
         }
     }
 
+    /// Every path from the root down to (and including) this path
+    pub(crate) fn root_down(&self) -> Vec<AbsPath> {
+        let mut paths = self.each_parent().collect::<Vec<_>>();
+        paths.reverse();
+        paths.push(self.clone());
+        paths
+    }
+
+    /// Canonical form of this path, or of the closest ancestor that can be canonicalized
+    ///
+    /// Facts about the filesystem a path would be created on come from here when the path
+    /// itself does not exist.
+    pub(crate) fn nearest_existing(&self) -> Option<PathBuf> {
+        self.nearest().map(|nearest| nearest.canonical)
+    }
+
+    /// The closest path, this one or an ancestor, that can be canonicalized
+    pub(crate) fn nearest(&self) -> Option<Nearest> {
+        std::iter::once(self.clone())
+            .chain(self.each_parent())
+            .find_map(|candidate| {
+                let canonical = std::fs::canonicalize(&candidate).ok()?;
+                let rest = self.0.strip_prefix(&candidate).ok()?;
+                let resolved = if rest.as_os_str().is_empty() {
+                    canonical.clone()
+                } else {
+                    canonical.join(rest)
+                };
+                Some(Nearest {
+                    canonical,
+                    resolved,
+                })
+            })
+    }
+
     #[allow(dead_code)]
     // Returns the last parent path
     pub(crate) fn root(&self) -> Self {
@@ -87,6 +128,14 @@ impl AsRef<Path> for AbsPath {
     }
 }
 
+/// The closest existing path found by `AbsPath::nearest`
+pub(crate) struct Nearest {
+    /// Canonical form of the path that exists on disk
+    pub(crate) canonical: PathBuf,
+    /// `canonical` joined with the components that do not exist
+    pub(crate) resolved: PathBuf,
+}
+
 /// Returns Err if `read_link` fails
 /// Returns Ok(None) if the path is not a symlink or if `fs::symlink_metadata` fails
 /// Otherwise returns Ok(Some(AbsPath)) with the target of the symlink
@@ -94,13 +143,7 @@ pub(crate) fn try_readlink(absolute: &AbsPath) -> Result<Option<AbsPath>, std::i
     let path = absolute.as_ref();
     if path.is_symlink() {
         std::fs::read_link(path)
-            .map(|target| {
-                if target.is_relative() {
-                    AbsPath(absolute.as_ref().join(target))
-                } else {
-                    AbsPath(target)
-                }
-            })
+            .map(|target| AbsPath(crate::symlink_chain::resolve_target(path, &target)))
             .map(Some)
     } else {
         Ok(None)
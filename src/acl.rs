@@ -0,0 +1,320 @@
+//! POSIX access control lists
+//!
+//! Linux stores ACLs in the `system.posix_acl_access` extended attribute as a version
+//! header followed by fixed size entries. Reading the attribute directly avoids a
+//! dependency on `libacl`. Mode bits alone can't explain access when an ACL grants a
+//! named user or group more than the mode allows, or when the mask takes it away.
+use crate::{ownership, permissions::Permissions, style, xattr};
+use std::{fmt::Display, path::Path};
+
+/// Extended attribute that holds the access ACL of a path
+pub(crate) const ACCESS: &str = "system.posix_acl_access";
+
+/// Extended attribute that holds the ACL new entries of a directory inherit
+pub(crate) const DEFAULT: &str = "system.posix_acl_default";
+
+/// `POSIX_ACL_XATTR_VERSION`, every ACL attribute starts with it
+const VERSION: u32 = 2;
+
+const TAG_USER_OBJ: u16 = 0x01;
+const TAG_USER: u16 = 0x02;
+const TAG_GROUP_OBJ: u16 = 0x04;
+const TAG_GROUP: u16 = 0x08;
+const TAG_MASK: u16 = 0x10;
+const TAG_OTHER: u16 = 0x20;
+
+/// Who an ACL entry applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum AclTag {
+    /// The owner, mirrors the owner mode bits
+    UserObj,
+    /// A named user by uid
+    User(u32),
+    /// The owning group, mirrors the group mode bits when there is no mask
+    GroupObj,
+    /// A named group by gid
+    Group(u32),
+    /// Upper bound for named users, the owning group, and named groups
+    Mask,
+    /// Everyone else, mirrors the other mode bits
+    Other,
+}
+
+/// A single ACL entry, such as `user:alice:rw-`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AclEntry {
+    pub(crate) tag: AclTag,
+    /// The `rwx` bits (`0o7`)
+    pub(crate) perms: u32,
+    /// Name of a named user or group from `/etc/passwd` or `/etc/group`
+    pub(crate) name: Option<String>,
+}
+
+/// Decoded POSIX ACL
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Acl {
+    pub(crate) entries: Vec<AclEntry>,
+}
+
+impl AclEntry {
+    /// Who the entry applies to
+    pub fn tag(&self) -> AclTag {
+        self.tag
+    }
+
+    /// Name of a named user or group, if listed
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Permissions the entry grants before the mask is applied
+    pub fn permissions(&self) -> Permissions {
+        bits_to_permissions(self.perms)
+    }
+
+    /// True for entries that the mask limits: named users, the owning group, and named groups
+    pub fn is_masked(&self) -> bool {
+        matches!(
+            self.tag,
+            AclTag::User(_) | AclTag::GroupObj | AclTag::Group(_)
+        )
+    }
+}
+
+impl Acl {
+    /// Reads and decodes an ACL attribute, following symlinks
+    ///
+    /// Returns `None` when the path has no ACL, the filesystem does not support them, or the
+    /// attribute cannot be read.
+    pub(crate) fn read(path: &Path, name: &str) -> Option<Self> {
+        xattr::get(path, name)
+            .ok()
+            .flatten()
+            .and_then(|bytes| Acl::parse(&bytes))
+    }
+
+    /// Like `read`, but also looks up the names of named users and groups
+    pub(crate) fn read_with_names(path: &Path, name: &str) -> Option<Self> {
+        let mut acl = Acl::read(path, name)?;
+        for entry in &mut acl.entries {
+            entry.name = match entry.tag {
+                AclTag::User(uid) => ownership::name_for_id("/etc/passwd", uid),
+                AclTag::Group(gid) => ownership::name_for_id("/etc/group", gid),
+                _ => None,
+            };
+        }
+        Some(acl)
+    }
+
+    /// Decodes the little endian `posix_acl_xattr` format
+    pub(crate) fn parse(bytes: &[u8]) -> Option<Self> {
+        let (header, rest) = bytes.split_first_chunk::<4>()?;
+        if u32::from_le_bytes(*header) != VERSION || rest.len() % 8 != 0 {
+            return None;
+        }
+        let entries = rest
+            .chunks_exact(8)
+            .map(|chunk| {
+                let tag = u16::from_le_bytes([chunk[0], chunk[1]]);
+                let perms = u16::from_le_bytes([chunk[2], chunk[3]]);
+                let id = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+                let tag = match tag {
+                    TAG_USER_OBJ => AclTag::UserObj,
+                    TAG_USER => AclTag::User(id),
+                    TAG_GROUP_OBJ => AclTag::GroupObj,
+                    TAG_GROUP => AclTag::Group(id),
+                    TAG_MASK => AclTag::Mask,
+                    TAG_OTHER => AclTag::Other,
+                    _ => return None,
+                };
+                Some(AclEntry {
+                    tag,
+                    perms: u32::from(perms) & 0o7,
+                    name: None,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Acl { entries })
+    }
+
+    /// Every entry in the order they are stored
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+
+    /// Permissions of the mask entry, if there is one
+    pub fn mask_permissions(&self) -> Option<Permissions> {
+        self.mask().map(bits_to_permissions)
+    }
+
+    /// Entries that the mask takes permissions away from, with the permissions it removes
+    pub fn masked_entries(&self) -> Vec<(&AclEntry, Permissions)> {
+        let Some(mask) = self.mask() else {
+            return vec![];
+        };
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_masked() && entry.perms & !mask != 0)
+            .map(|entry| (entry, bits_to_permissions(entry.perms & !mask)))
+            .collect()
+    }
+
+    /// Describes what the mask removes, such as "mask `r--` removes write from `user:alice:rw-`"
+    pub(crate) fn mask_notes(&self) -> Vec<String> {
+        let Some(mask) = self.mask() else {
+            return vec![];
+        };
+        self.masked_entries()
+            .into_iter()
+            .map(|(entry, removed)| {
+                let removed = [
+                    (removed.read, "read"),
+                    (removed.write, "write"),
+                    (removed.execute, "execute"),
+                ]
+                .into_iter()
+                .filter_map(|(removed, name)| removed.then_some(name))
+                .collect::<Vec<_>>()
+                .join(" and ");
+                format!(
+                    "mask `{mask}` removes {removed} from `{entry}`",
+                    mask = bits_string(mask)
+                )
+            })
+            .collect()
+    }
+
+    /// Permission bits of the mask entry, if there is one
+    fn mask(&self) -> Option<u32> {
+        self.entries
+            .iter()
+            .find(|entry| entry.tag == AclTag::Mask)
+            .map(|entry| entry.perms)
+    }
+
+    /// The `rwx` bits (`0o7`) that apply to a user, following the POSIX ACL access check
+    ///
+    /// The owner entry wins, then a named user entry, then every matching group entry
+    /// combined, and finally other. Named users and groups are limited by the mask. Root is
+    /// not special cased here.
+    pub(crate) fn access_bits(&self, owner: u32, group: u32, uid: u32, gids: &[u32]) -> u32 {
+        let mask = self.mask().unwrap_or(0o7);
+        let find = |tag: AclTag| {
+            self.entries
+                .iter()
+                .find(|entry| entry.tag == tag)
+                .map(|entry| entry.perms)
+        };
+
+        if uid == owner {
+            return find(AclTag::UserObj).unwrap_or(0);
+        }
+        if let Some(perms) = find(AclTag::User(uid)) {
+            return perms & mask;
+        }
+        let groups = self
+            .entries
+            .iter()
+            .filter(|entry| match entry.tag {
+                AclTag::GroupObj => gids.contains(&group),
+                AclTag::Group(gid) => gids.contains(&gid),
+                _ => false,
+            })
+            .map(|entry| entry.perms)
+            .reduce(|all, perms| all | perms);
+        match groups {
+            Some(perms) => perms & mask,
+            None => find(AclTag::Other).unwrap_or(0),
+        }
+    }
+}
+
+fn bits_to_permissions(bits: u32) -> Permissions {
+    Permissions::new(bits & 0o4 != 0, bits & 0o2 != 0, bits & 0o1 != 0)
+}
+
+/// Formats `rwx` bits (`0o7`) like `r-x`
+fn bits_string(bits: u32) -> String {
+    style::mode_string(bits & 0o7).split_off(6)
+}
+
+impl Display for AclEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let qualifier = |id: u32| self.name.clone().unwrap_or_else(|| id.to_string());
+        let (kind, qualifier) = match self.tag {
+            AclTag::UserObj => ("user", String::new()),
+            AclTag::User(uid) => ("user", qualifier(uid)),
+            AclTag::GroupObj => ("group", String::new()),
+            AclTag::Group(gid) => ("group", qualifier(gid)),
+            AclTag::Mask => ("mask", String::new()),
+            AclTag::Other => ("other", String::new()),
+        };
+        write!(f, "{kind}:{qualifier}:{}", bits_string(self.perms))
+    }
+}
+
+impl Display for Acl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| format!("`{entry}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{entries}")
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    /// Encodes entries as `(tag, perms, id)` in the `posix_acl_xattr` format
+    pub(crate) fn encode(entries: &[(u16, u16, u32)]) -> Vec<u8> {
+        let mut bytes = VERSION.to_le_bytes().to_vec();
+        for (tag, perms, id) in entries {
+            bytes.extend(tag.to_le_bytes());
+            bytes.extend(perms.to_le_bytes());
+            bytes.extend(id.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_and_mask() {
+        // user::rwx user:1002:rw- group::r-x group:50:-w- mask::r-- other::---
+        let bytes = encode(&[
+            (TAG_USER_OBJ, 0o7, u32::MAX),
+            (TAG_USER, 0o6, 1002),
+            (TAG_GROUP_OBJ, 0o5, u32::MAX),
+            (TAG_GROUP, 0o2, 50),
+            (TAG_MASK, 0o4, u32::MAX),
+            (TAG_OTHER, 0o0, u32::MAX),
+        ]);
+        let acl = Acl::parse(&bytes).unwrap();
+        assert_eq!(AclTag::User(1002), acl.entries[1].tag);
+        assert_eq!(Some(0o4), acl.mask());
+
+        // Owner is not limited by the mask
+        assert_eq!(0o7, acl.access_bits(1000, 100, 1000, &[100]));
+        // Named user is limited by the mask
+        assert_eq!(0o4, acl.access_bits(1000, 100, 1002, &[1002]));
+        // Matching groups combine before the mask
+        assert_eq!(0o4, acl.access_bits(1000, 100, 1003, &[100, 50]));
+        assert_eq!(0o0, acl.access_bits(1000, 100, 1003, &[50]));
+        assert_eq!(0o0, acl.access_bits(1000, 100, 1003, &[1003]));
+
+        assert_eq!(None, Acl::parse(&bytes[..bytes.len() - 1]));
+        assert_eq!(None, Acl::parse(&encode(&[(0x40, 0o7, 0)])));
+    }
+}
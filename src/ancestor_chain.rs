@@ -0,0 +1,365 @@
+//! Facts about every component of a path, from the root down
+//!
+//! Similar to `namei -l`. A path can only be reached when every directory above it exists,
+//! is a directory (or a symlink to one), and has the `execute` permission. The immediate
+//! parent isn't enough to explain a failure when the problem is further up the chain.
+use crate::{
+    abs_path::AbsPath,
+    permissions::Permissions,
+    resolved_metadata::{ResolvedMetadata, ResolvedType},
+    style,
+};
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Shows every component of a path from the root down when `Display`ed.
+#[derive(Debug)]
+pub struct AncestorChain {
+    path: PathBuf,
+    ancestors: Vec<Ancestor>,
+}
+
+/// A single component of an `AncestorChain`
+#[derive(Debug)]
+pub struct Ancestor {
+    path: AbsPath,
+    symlink_target: Option<PathBuf>,
+    file_type: Option<ResolvedType>,
+    resolved_type: Option<ResolvedType>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    user: Option<String>,
+    group: Option<String>,
+    mode: Option<u32>,
+    permissions: Option<Permissions>,
+    error: Option<std::io::Error>,
+    breaks_traversal: Option<TraversalBreak>,
+}
+
+/// Why a component stops the path below it from being reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraversalBreak {
+    /// The component does not exist, or is a symlink to a path that does not exist
+    DoesNotExist,
+    /// A component above the target is not a directory
+    NotADirectory,
+    /// A directory above the target cannot be searched
+    MissingExecute,
+    /// The component could not be inspected
+    Inaccessible,
+}
+
+impl Display for TraversalBreak {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraversalBreak::DoesNotExist => write!(f, "does not exist"),
+            TraversalBreak::NotADirectory => write!(f, "not a directory"),
+            TraversalBreak::MissingExecute => {
+                write!(f, "lacks execute permission, so it cannot be searched")
+            }
+            TraversalBreak::Inaccessible => write!(f, "cannot be accessed"),
+        }
+    }
+}
+
+impl AncestorChain {
+    /// Inspects every component of the path from the root down
+    ///
+    /// Stops after the first component that cannot be inspected, such as one that does
+    /// not exist.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let mut ancestors = vec![];
+        if let Ok(absolute) = AbsPath::new(path) {
+            let components = absolute.root_down();
+            let last = components.len() - 1;
+            for (index, component) in components.into_iter().enumerate() {
+                let ancestor = Ancestor::new(component, index == last);
+                let stop = ancestor.error.is_some();
+                ancestors.push(ancestor);
+                if stop {
+                    break;
+                }
+            }
+        }
+
+        AncestorChain {
+            path: path.to_path_buf(),
+            ancestors,
+        }
+    }
+
+    /// The path as it was given
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every inspected component, from the root down
+    pub fn ancestors(&self) -> &[Ancestor] {
+        &self.ancestors
+    }
+
+    /// The first component that stops the path from being reached, and why
+    pub fn first_break(&self) -> Option<(&Ancestor, TraversalBreak)> {
+        self.ancestors.iter().find_map(|ancestor| {
+            ancestor
+                .breaks_traversal
+                .map(|breaks_traversal| (ancestor, breaks_traversal))
+        })
+    }
+}
+
+impl Ancestor {
+    fn new(path: AbsPath, is_target: bool) -> Self {
+        let mut ancestor = Ancestor {
+            path,
+            symlink_target: None,
+            file_type: None,
+            resolved_type: None,
+            uid: None,
+            gid: None,
+            user: None,
+            group: None,
+            mode: None,
+            permissions: None,
+            error: None,
+            breaks_traversal: None,
+        };
+        let metadata = match std::fs::symlink_metadata(&ancestor.path) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                ancestor.breaks_traversal = Some(if error.kind() == ErrorKind::NotFound {
+                    TraversalBreak::DoesNotExist
+                } else {
+                    TraversalBreak::Inaccessible
+                });
+                ancestor.error = Some(error);
+                return ancestor;
+            }
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            ancestor.uid = Some(metadata.uid());
+            ancestor.gid = Some(metadata.gid());
+            ancestor.user = crate::ownership::name_for_id("/etc/passwd", metadata.uid());
+            ancestor.group = crate::ownership::name_for_id("/etc/group", metadata.gid());
+            ancestor.mode = Some(metadata.mode());
+        }
+        ancestor.file_type = Some(ResolvedType::from_metadata(&metadata));
+        if metadata.is_symlink() {
+            ancestor.symlink_target = std::fs::read_link(&ancestor.path).ok();
+        }
+        ancestor.resolved_type = ResolvedMetadata::new(&ancestor.path)
+            .ok()
+            .map(|metadata| metadata.resolved_type());
+        ancestor.permissions = Some(Permissions::effective(ancestor.path.as_ref()));
+
+        ancestor.breaks_traversal = match (&ancestor.resolved_type, ancestor.permissions) {
+            // Broken symlink
+            (None, _) => Some(TraversalBreak::DoesNotExist),
+            _ if is_target => None,
+            (Some(kind), _) if !kind.is_dir() => Some(TraversalBreak::NotADirectory),
+            (_, Some(permissions)) if !permissions.execute() => {
+                Some(TraversalBreak::MissingExecute)
+            }
+            _ => None,
+        };
+        ancestor
+    }
+
+    /// Absolute path of the component
+    pub fn path(&self) -> &Path {
+        self.path.as_ref()
+    }
+
+    /// True when the component itself is a symlink
+    pub fn is_symlink(&self) -> bool {
+        self.symlink_target.is_some()
+    }
+
+    /// Where the component points to when it is a symlink, as stored in the link
+    pub fn symlink_target(&self) -> Option<&Path> {
+        self.symlink_target.as_deref()
+    }
+
+    /// Type of the component itself, `Symlink` when it is a symlink
+    pub fn file_type(&self) -> Option<ResolvedType> {
+        self.file_type.clone()
+    }
+
+    /// Type of the component after following symlinks
+    pub fn resolved_type(&self) -> Option<ResolvedType> {
+        self.resolved_type.clone()
+    }
+
+    /// Numeric user id of the owner, on unix
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// Numeric group id of the owner, on unix
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// Name of the owner from `/etc/passwd`, on unix
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Name of the group from `/etc/group`, on unix
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// Unix mode, including the file type bits
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// Owner and group names, or their ids when they have no name
+    pub(crate) fn owner_and_group(&self) -> Option<(String, String)> {
+        let name = |name: &Option<String>, id: Option<u32>| {
+            name.clone().or_else(|| id.map(|id| id.to_string()))
+        };
+        name(&self.user, self.uid).zip(name(&self.group, self.gid))
+    }
+
+    /// Effective permissions of the component
+    pub fn permissions(&self) -> Option<Permissions> {
+        self.permissions
+    }
+
+    /// Error from inspecting the component, when it could not be inspected
+    pub fn error(&self) -> Option<&std::io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Why this component stops the path below it from being reached, if it does
+    pub fn breaks_traversal(&self) -> Option<TraversalBreak> {
+        self.breaks_traversal
+    }
+}
+
+impl Display for Ancestor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(error) = &self.error {
+            return write!(f, "{path}: {error}", path = self.path);
+        }
+        if let (Some(mode), Some((owner, group))) = (self.mode, self.owner_and_group()) {
+            write!(f, "{} ", listing(mode, &owner, &group))?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(target) = &self.symlink_target {
+            write!(f, " -> `{}`", target.display())?;
+        }
+        if let Some(permissions) = &self.permissions {
+            write!(f, " ({})", style::perms(permissions))?;
+        }
+        Ok(())
+    }
+}
+
+/// Mode, owner, and group of a component the way `namei -l` lists them, such as
+/// `drwxr-x--- root:adm`
+pub(crate) fn listing(mode: u32, owner: &str, group: &str) -> String {
+    format!("{} {owner}:{group}", style::mode_string(mode))
+}
+
+impl Display for AncestorChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "ancestors of `{}` from the root down:",
+            self.path.display()
+        )?;
+        let first_break = self
+            .ancestors
+            .iter()
+            .position(|ancestor| ancestor.breaks_traversal.is_some());
+        for (index, ancestor) in self.ancestors.iter().enumerate() {
+            match ancestor.breaks_traversal {
+                Some(reason) if Some(index) == first_break => writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("{ancestor} <- breaks traversal: {reason}"))
+                )?,
+                _ => writeln!(f, "{}", style::bullet(ancestor.to_string()))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_root_down_to_missing() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("a").join("b.txt");
+
+        let chain = AncestorChain::new(&path);
+        let ancestors = chain.ancestors();
+        assert_eq!(Path::new("/"), ancestors[0].path());
+        let last = ancestors.last().unwrap();
+        assert_eq!(tempdir.path().join("a"), last.path());
+        assert_eq!(
+            Some(ErrorKind::NotFound),
+            last.error().map(std::io::Error::kind)
+        );
+
+        let (first, reason) = chain.first_break().unwrap();
+        assert_eq!(tempdir.path().join("a"), first.path());
+        assert_eq!(TraversalBreak::DoesNotExist, reason);
+        assert!(chain.to_string().contains(&format!(
+            " - `{}`: No such file or directory (os error 2) <- breaks traversal: does not exist\n",
+            tempdir.path().join("a").display()
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_symlink_and_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let file = tempdir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+        let link = tempdir.path().join("link");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        let chain = AncestorChain::new(link.join("nested.txt"));
+        let (ancestor, reason) = chain.first_break().unwrap();
+        assert_eq!(link, ancestor.path());
+        assert!(ancestor.is_symlink());
+        assert_eq!(Some(file.as_path()), ancestor.symlink_target());
+        assert_eq!(TraversalBreak::NotADirectory, reason);
+
+        let chain = AncestorChain::new(&file);
+        let target = chain.ancestors().last().unwrap();
+        assert_eq!(Some(0o100640), target.mode());
+        assert!(chain.first_break().is_none());
+        let (owner, group) = target.owner_and_group().unwrap();
+        assert_eq!(
+            target
+                .user()
+                .map(String::from)
+                .unwrap_or(target.uid().unwrap().to_string()),
+            owner
+        );
+        assert!(chain
+            .to_string()
+            .contains(&format!("-rw-r----- {owner}:{group} `{}`", file.display())));
+    }
+}
@@ -19,6 +19,12 @@ impl CanonicalPath {
         let canonical = abs_path.as_ref().canonicalize()?;
         Ok(CanonicalPath(canonical))
     }
+
+    /// Trusts that a path recorded from a prior `CanonicalPath` is still canonical
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_recorded(path: PathBuf) -> Self {
+        CanonicalPath(path)
+    }
 }
 
 impl AsRef<Path> for CanonicalPath {
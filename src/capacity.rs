@@ -0,0 +1,297 @@
+//! Free space and inodes of the filesystem that holds a path (unix only)
+//!
+//! "No space left on device" (`ENOSPC`) happens when a filesystem runs out of space or out of
+//! inodes. Lots of small files can use up every inode while gigabytes of space remain, and
+//! ext4 reserves a share of the space for root, so a disk can be full for one user and not
+//! another. Neither shows up in the permissions or the tree.
+use crate::style;
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Share of space or inodes in use at which a filesystem is called out as nearly full
+const NEARLY_FULL_PERCENT: u64 = 95;
+
+/// Capacity of a filesystem from `statvfs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capacity {
+    /// The path `statvfs` was called on, the closest existing ancestor when the path does
+    /// not exist
+    pub(crate) path: PathBuf,
+    pub(crate) total_bytes: u64,
+    /// Free for privileged users, including the reserved space
+    pub(crate) free_bytes: u64,
+    /// Free for unprivileged users
+    pub(crate) available_bytes: u64,
+    /// Zero when the filesystem does not limit inodes, such as btrfs
+    pub(crate) total_inodes: u64,
+    pub(crate) free_inodes: u64,
+    pub(crate) available_inodes: u64,
+}
+
+impl Capacity {
+    /// Reads the capacity of the filesystem that holds the path, or that it would be created on
+    #[cfg(unix)]
+    // Field types differ between platforms, they are already `u64` on 64-bit Linux
+    #[allow(clippy::useless_conversion)]
+    pub(crate) fn new(path: &Path) -> Option<Self> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = crate::abs_path::AbsPath::new(path)
+            .ok()?
+            .nearest_existing()?;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `statvfs` is plain data, zeroed is a valid value
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: `c_path` is nul terminated and `stat` is a valid buffer
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        // Block counts are in units of the fragment size
+        let fragment = u64::from(stat.f_frsize);
+        Some(Capacity {
+            path,
+            total_bytes: u64::from(stat.f_blocks) * fragment,
+            free_bytes: u64::from(stat.f_bfree) * fragment,
+            available_bytes: u64::from(stat.f_bavail) * fragment,
+            total_inodes: u64::from(stat.f_files),
+            free_inodes: u64::from(stat.f_ffree),
+            available_inodes: u64::from(stat.f_favail),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new(_path: &Path) -> Option<Self> {
+        None
+    }
+
+    /// True for errors about space, `statvfs` is not worth calling for others
+    pub(crate) fn explains(kind: ErrorKind) -> bool {
+        matches!(kind, ErrorKind::StorageFull | ErrorKind::QuotaExceeded)
+    }
+
+    /// The path the capacity was read for, the closest existing ancestor when the path does
+    /// not exist
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the filesystem
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Free space, including the space reserved for privileged users
+    pub fn free_bytes(&self) -> u64 {
+        self.free_bytes
+    }
+
+    /// Free space that unprivileged users can write to
+    pub fn available_bytes(&self) -> u64 {
+        self.available_bytes
+    }
+
+    /// Free space that only privileged users, root by default, can write to
+    pub fn reserved_bytes(&self) -> u64 {
+        self.free_bytes.saturating_sub(self.available_bytes)
+    }
+
+    /// Number of inodes, zero when the filesystem does not limit them
+    pub fn total_inodes(&self) -> u64 {
+        self.total_inodes
+    }
+
+    /// Inodes left, every file, directory, and symlink needs one
+    pub fn free_inodes(&self) -> u64 {
+        self.free_inodes
+    }
+
+    /// Share of space in use, counting reserved space as unavailable like `df` does
+    pub fn percent_used(&self) -> Option<u64> {
+        let used = self.total_bytes.saturating_sub(self.free_bytes);
+        percent(used, used + self.available_bytes)
+    }
+
+    /// Share of inodes in use, `None` when the filesystem does not limit them
+    pub fn percent_inodes_used(&self) -> Option<u64> {
+        percent(
+            self.total_inodes.saturating_sub(self.free_inodes),
+            self.total_inodes,
+        )
+    }
+
+    /// Facts that explain `ENOSPC`, such as running out of inodes while space remains
+    pub(crate) fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.total_inodes > 0 && self.available_inodes == 0 {
+            warnings.push(format!(
+                "0 inodes free while {} of space remains, creating files and directories fails with ENOSPC",
+                style::bytes(self.available_bytes)
+            ));
+        } else if let Some(percent) = self
+            .percent_inodes_used()
+            .filter(|percent| *percent >= NEARLY_FULL_PERCENT)
+        {
+            warnings.push(format!(
+                "{percent}% of inodes used, {} left",
+                self.available_inodes
+            ));
+        }
+
+        if self.total_bytes > 0 && self.free_bytes == 0 {
+            warnings.push("0 bytes free, writes fail with ENOSPC".to_string());
+        } else if self.total_bytes > 0 && self.available_bytes == 0 {
+            warnings.push(format!(
+                "0 bytes available, the {} left are reserved for root, so writes by other users fail with ENOSPC",
+                style::bytes(self.free_bytes)
+            ));
+        } else if let Some(percent) = self
+            .percent_used()
+            .filter(|percent| *percent >= NEARLY_FULL_PERCENT)
+        {
+            warnings.push(format!(
+                "{percent}% of space used, {} available",
+                style::bytes(self.available_bytes)
+            ));
+        }
+        warnings
+    }
+}
+
+/// Rounds up like `df` so that a nearly full filesystem never shows as less full
+fn percent(used: u64, total: u64) -> Option<u64> {
+    (total > 0).then(|| (used * 100).div_ceil(total))
+}
+
+impl Display for Capacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{available} available of {total}",
+            available = style::bytes(self.available_bytes),
+            total = style::bytes(self.total_bytes)
+        )?;
+        let mut details = vec![];
+        if let Some(percent) = self.percent_used() {
+            details.push(format!("{percent}% used"));
+        }
+        if self.reserved_bytes() > 0 {
+            details.push(format!(
+                "{} reserved for root",
+                style::bytes(self.reserved_bytes())
+            ));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        if self.total_inodes > 0 {
+            write!(
+                f,
+                ", {} of {} inodes free",
+                self.free_inodes, self.total_inodes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    fn capacity() -> Capacity {
+        Capacity {
+            path: PathBuf::from("/data"),
+            total_bytes: 50_000_000_000,
+            free_bytes: 14_500_000_000,
+            available_bytes: 12_000_000_000,
+            total_inodes: 3_276_800,
+            free_inodes: 0,
+            available_inodes: 0,
+        }
+    }
+
+    #[test]
+    fn test_out_of_inodes_with_space_left() {
+        let capacity = capacity();
+        assert_eq!(2_500_000_000, capacity.reserved_bytes());
+        assert_eq!(Some(75), capacity.percent_used());
+        assert_eq!(Some(100), capacity.percent_inodes_used());
+        assert_eq!(
+            vec!["0 inodes free while 12.0 GB of space remains, creating files and directories fails with ENOSPC"],
+            capacity.warnings()
+        );
+        assert_eq!(
+            "12.0 GB available of 50.0 GB (75% used, 2.5 GB reserved for root), 0 of 3276800 inodes free",
+            capacity.to_string()
+        );
+    }
+
+    #[test]
+    fn test_space_reserved_for_root() {
+        let capacity = Capacity {
+            available_bytes: 0,
+            free_inodes: 1_000_000,
+            available_inodes: 1_000_000,
+            ..capacity()
+        };
+        assert_eq!(Some(100), capacity.percent_used());
+        assert_eq!(
+            vec!["0 bytes available, the 14.5 GB left are reserved for root, so writes by other users fail with ENOSPC"],
+            capacity.warnings()
+        );
+
+        let capacity = Capacity {
+            total_inodes: 0,
+            free_inodes: 0,
+            available_inodes: 0,
+            available_bytes: 800_000,
+            free_bytes: 800_000,
+            ..capacity
+        };
+        assert_eq!(None, capacity.percent_inodes_used());
+        assert_eq!(
+            vec!["100% of space used, 800.0 kB available"],
+            capacity.warnings()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_missing_path_uses_existing_ancestor() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let capacity = Capacity::new(&tempdir.path().join("missing").join("file.txt")).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(tempdir.path()).unwrap(),
+            capacity.path()
+        );
+        assert!(capacity.total_bytes() > 0);
+        assert!(capacity.available_bytes() <= capacity.free_bytes());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shown_for_storage_full() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+
+        let facts = crate::PathFacts::new(&path);
+        assert!(facts.capacity().is_none());
+        let facts = crate::PathFacts::for_operation(&path, crate::Operation::Read);
+        assert!(facts.capacity().is_none());
+        let facts = crate::PathFacts::for_operation(&path, crate::Operation::Write);
+        assert!(facts.capacity().is_some());
+
+        let error = std::io::Error::from(ErrorKind::StorageFull);
+        let facts = crate::PathFacts::with_error(&path, &error);
+        assert!(facts.to_string().contains(&format!(
+            " - Filesystem of `{}`: ",
+            std::fs::canonicalize(tempdir.path()).unwrap().display()
+        )));
+    }
+}
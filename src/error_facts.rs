@@ -0,0 +1,539 @@
+//! Facts selected by the error that the caller saw
+//!
+//! The same path facts can explain many different errors. Knowing the original
+//! `std::io::Error` lets us lead with the facts that explain that specific error,
+//! for example which component is missing for `NotFound` or which ancestor blocks
+//! traversal for `PermissionDenied`.
+use crate::{
+    abs_path::AbsPath,
+    ancestor_chain::{self, AncestorChain},
+    path_state::{Existence, PathState},
+    permissions::Permissions,
+    resolved_metadata::ResolvedType,
+    style,
+    symlink_chain::SymlinkChain,
+    PathFacts,
+};
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// The error a caller reported and the facts that focus on it
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ErrorFacts {
+    #[cfg_attr(feature = "serde", serde(with = "crate::schema::error_kind"))]
+    pub(crate) kind: ErrorKind,
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) raw_os_error: Option<i32>,
+    pub(crate) focus: Option<Focus>,
+    /// The error came from an operation on several paths and agrees with the facts of
+    /// another one, so it is not checked for contradictions against this path
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub(crate) unattributed: bool,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub(crate) enum Focus {
+    /// The first component of the path that does not exist
+    Missing { existing: PathBuf, missing: PathBuf },
+    /// Effective permissions of every ancestor, from the root down
+    PermissionChain { ancestors: Vec<AncestorPermissions> },
+    /// The path is a symlink whose final target does not exist
+    BrokenSymlink { link: PathBuf, target: PathBuf },
+    /// The first component of the path that exists but is not a directory, `is_target` when
+    /// it is the path itself, such as `read_dir` on a file
+    NotADirectory { file: PathBuf, is_target: bool },
+    /// Byte lengths of the path and its longest component
+    NameTooLong {
+        component: String,
+        component_bytes: usize,
+        path_bytes: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct AncestorPermissions {
+    pub(crate) path: PathBuf,
+    pub(crate) permissions: Permissions,
+    /// `namei -l` style mode, owner, and group, `None` off unix
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) listing: Option<String>,
+}
+
+/// Facts gathered after a failure that disagree with the error that was reported
+///
+/// Files on disk can change between when an operation fails and when facts are gathered
+/// (TOCTOU). When that happens the facts are still true, but they describe a different
+/// moment than the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Contradiction {
+    /// The error was `NotFound` but the path exists and resolves now
+    ///
+    /// Not reported for a symlink whose target is missing, that is what `NotFound` means.
+    ExistsNow,
+    /// The error was `AlreadyExists` but the path does not exist now
+    MissingNow,
+    /// The error was `PermissionDenied` but effective permissions allow access now
+    PermittedNow,
+    /// The error was `NotADirectory` but the path and every prior path are directories now
+    DirectoriesNow,
+    /// The error was `IsADirectory` but the path is not a directory now
+    NotADirectoryNow,
+}
+
+impl Display for Contradiction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Contradiction::ExistsNow => write!(
+                f,
+                "the error said the path was not found, but it exists now, so it was probably created after the failure"
+            ),
+            Contradiction::MissingNow => write!(
+                f,
+                "the error said the path already exists, but it does not exist now, so it was probably removed after the failure"
+            ),
+            Contradiction::PermittedNow => write!(
+                f,
+                "the error said permission denied, but the current effective permissions allow access, so permissions changed after the failure or access is restricted by something other than permissions"
+            ),
+            Contradiction::DirectoriesNow => write!(
+                f,
+                "the error said a prior path is not a directory, but they are all directories now, so one was probably replaced after the failure"
+            ),
+            Contradiction::NotADirectoryNow => write!(
+                f,
+                "the error said the path is a directory, but it is not a directory now, so it was probably replaced after the failure"
+            ),
+        }
+    }
+}
+
+impl ErrorFacts {
+    /// Compares the reported error with the facts gathered afterwards
+    pub(crate) fn contradictions(&self, facts: &PathFacts) -> Vec<Contradiction> {
+        let mut contradictions = vec![];
+        if self.unattributed {
+            return contradictions;
+        }
+        match self.kind {
+            ErrorKind::NotFound if facts.resolved_type().is_some() => {
+                contradictions.push(Contradiction::ExistsNow)
+            }
+            ErrorKind::AlreadyExists if facts.existence() == Existence::Missing => {
+                contradictions.push(Contradiction::MissingNow)
+            }
+            ErrorKind::PermissionDenied if self.permitted(facts) => {
+                contradictions.push(Contradiction::PermittedNow)
+            }
+            ErrorKind::NotADirectory
+                if self.focus.is_none()
+                    && matches!(facts.state(), PathState::Exists | PathState::DoesNotExist)
+                    && !facts
+                        .resolved_type()
+                        .is_some_and(|kind| kind != ResolvedType::Dir) =>
+            {
+                contradictions.push(Contradiction::DirectoriesNow)
+            }
+            ErrorKind::IsADirectory
+                if facts
+                    .resolved_type()
+                    .is_some_and(|kind| kind != ResolvedType::Dir) =>
+            {
+                contradictions.push(Contradiction::NotADirectoryNow)
+            }
+            _ => {}
+        }
+        contradictions
+    }
+
+    /// True when the path and every ancestor allow access now
+    fn permitted(&self, facts: &PathFacts) -> bool {
+        let target = facts.permissions().is_some_and(|permissions| {
+            permissions.read()
+                && permissions.write()
+                && (permissions.execute() || facts.resolved_type() != Some(ResolvedType::Dir))
+        });
+        let ancestors = match &self.focus {
+            Some(Focus::PermissionChain { ancestors }) => ancestors
+                .iter()
+                .rev()
+                .skip(1)
+                .all(|ancestor| ancestor.permissions.execute()),
+            _ => false,
+        };
+        target && ancestors
+    }
+
+    pub(crate) fn new(path: &Path, error: &std::io::Error) -> Self {
+        let kind = error.kind();
+        let raw_os_error = error.raw_os_error();
+        let focus = AbsPath::new(path).ok().and_then(|absolute| {
+            if name_too_long(error) {
+                name_lengths(&absolute)
+            } else {
+                match kind {
+                    ErrorKind::NotFound => {
+                        first_missing(&absolute).or_else(|| broken_symlink(&absolute))
+                    }
+                    ErrorKind::PermissionDenied => Some(permission_chain(&absolute)),
+                    ErrorKind::NotADirectory => first_not_dir(&absolute),
+                    _ => None,
+                }
+            }
+        });
+
+        ErrorFacts {
+            kind,
+            raw_os_error,
+            focus,
+            unattributed: false,
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn name_too_long(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ENAMETOOLONG)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn name_too_long(_error: &std::io::Error) -> bool {
+    false
+}
+
+fn first_missing(absolute: &AbsPath) -> Option<Focus> {
+    let paths = absolute.root_down();
+    paths.windows(2).find_map(|pair| {
+        let (existing, missing) = (&pair[0], &pair[1]);
+        match std::fs::symlink_metadata(missing) {
+            Err(error) if error.kind() == ErrorKind::NotFound => Some(Focus::Missing {
+                existing: existing.as_ref().to_path_buf(),
+                missing: missing.as_ref().to_path_buf(),
+            }),
+            _ => None,
+        }
+    })
+}
+
+fn broken_symlink(absolute: &AbsPath) -> Option<Focus> {
+    let chain = SymlinkChain::new(absolute);
+    chain
+        .broken_at()
+        .filter(|hop| !hop.target_exists())
+        .map(|hop| Focus::BrokenSymlink {
+            link: hop.link().to_path_buf(),
+            target: hop.target().to_path_buf(),
+        })
+}
+
+fn first_not_dir(absolute: &AbsPath) -> Option<Focus> {
+    let paths = absolute.root_down();
+    let last = paths.len() - 1;
+    paths
+        .iter()
+        .position(|path| std::fs::metadata(path).is_ok_and(|metadata| !metadata.is_dir()))
+        .map(|index| Focus::NotADirectory {
+            file: paths[index].as_ref().to_path_buf(),
+            is_target: index == last,
+        })
+}
+
+fn permission_chain(absolute: &AbsPath) -> Focus {
+    Focus::PermissionChain {
+        ancestors: AncestorChain::new(absolute)
+            .ancestors()
+            .iter()
+            .filter_map(|ancestor| {
+                Some(AncestorPermissions {
+                    path: ancestor.path().to_path_buf(),
+                    permissions: ancestor.permissions()?,
+                    listing: ancestor.mode().zip(ancestor.owner_and_group()).map(
+                        |(mode, (owner, group))| ancestor_chain::listing(mode, &owner, &group),
+                    ),
+                })
+            })
+            .collect(),
+    }
+}
+
+fn name_lengths(absolute: &AbsPath) -> Option<Focus> {
+    let path = absolute.as_ref();
+    path.components()
+        .map(|component| component.as_os_str())
+        .max_by_key(|name| name.len())
+        .map(|longest| Focus::NameTooLong {
+            component: longest.to_string_lossy().to_string(),
+            component_bytes: longest.len(),
+            path_bytes: path.as_os_str().len(),
+        })
+}
+
+impl Display for ErrorFacts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.focus {
+            Some(Focus::Missing { existing, missing }) => writeln!(
+                f,
+                "{}",
+                style::bullet(format!(
+                    "First missing component `{name}` in `{existing}`",
+                    name = style::filename_or_path(missing),
+                    existing = existing.display()
+                ))
+            ),
+            Some(Focus::PermissionChain { ancestors }) => {
+                let chain = ancestors
+                    .iter()
+                    .map(|ancestor| {
+                        format!(
+                            "{listing}`{path}` ({permissions})",
+                            listing = ancestor
+                                .listing
+                                .as_ref()
+                                .map(|listing| format!("{listing} "))
+                                .unwrap_or_default(),
+                            path = ancestor.path.display(),
+                            permissions = style::perms(&ancestor.permissions)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("Permissions from the root down:\n{chain}"))
+                )
+            }
+            Some(Focus::BrokenSymlink { link, target }) => writeln!(
+                f,
+                "{}",
+                style::bullet(format!(
+                    "Symlink `{link}` points to `{target}`, which does not exist",
+                    link = link.display(),
+                    target = target.display()
+                ))
+            ),
+            Some(Focus::NotADirectory { file, is_target }) => writeln!(
+                f,
+                "{}",
+                style::bullet(format!(
+                    "{which} is not a directory `{file}`",
+                    which = if *is_target { "Path" } else { "Prior path" },
+                    file = file.display()
+                ))
+            ),
+            Some(Focus::NameTooLong {
+                component,
+                component_bytes,
+                path_bytes,
+            }) => {
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!(
+                        "Longest component is {component_bytes} bytes `{component}`"
+                    ))
+                )?;
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("Path is {path_bytes} bytes"))
+                )
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathFacts;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_not_found_shows_first_missing_component() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("a").join("b").join("c.txt");
+        let error = std::fs::read(&path).unwrap_err();
+
+        let facts = PathFacts::with_error(&path, &error);
+        let rendered = facts.to_string();
+        let mut lines = rendered.lines();
+        assert_eq!(
+            Some(format!("cannot access `{}`", path.display()).as_str()),
+            lines.next()
+        );
+        assert_eq!(
+            Some(
+                format!(
+                    " - First missing component `a` in `{}`",
+                    tempdir.path().display()
+                )
+                .as_str()
+            ),
+            lines.next()
+        );
+    }
+
+    #[test]
+    fn test_not_a_directory_names_the_file() {
+        let tempdir = tempfile::tempdir().unwrap();
+        std::fs::write(tempdir.path().join("a"), "").unwrap();
+        let path = tempdir.path().join("a").join("b.txt");
+        let error = std::fs::read(&path).unwrap_err();
+
+        let facts = PathFacts::with_error(&path, &error);
+        assert!(facts.to_string().contains(&format!(
+            " - Prior path is not a directory `{}`\n",
+            tempdir.path().join("a").display()
+        )));
+    }
+
+    #[test]
+    fn test_read_dir_on_a_file() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        let error = std::fs::read_dir(&path).unwrap_err();
+        assert_eq!(ErrorKind::NotADirectory, error.kind());
+
+        let facts = PathFacts::with_error(&path, &error);
+        assert!(facts.contradictions().is_empty());
+        let rendered = facts.to_string();
+        assert!(!rendered.contains("Contradiction"));
+        assert!(rendered.contains(&format!(
+            " - Path is not a directory `{}`\n",
+            path.display()
+        )));
+
+        let error = crate::fs::read_dir(&path).unwrap_err();
+        assert!(!error.to_string().contains("Contradiction"));
+        assert!(error.to_string().contains(" - Path is not a directory `"));
+
+        let slash = tempdir.path().join("file.txt/");
+        let error = std::fs::read(&slash).unwrap_err();
+        let facts = PathFacts::with_error(&slash, &error);
+        assert!(facts.contradictions().is_empty());
+    }
+
+    #[test]
+    fn test_permission_denied_shows_chain() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        let error = std::io::Error::from(ErrorKind::PermissionDenied);
+
+        let facts = PathFacts::with_error(&path, &error);
+        let rendered = facts.to_string();
+        assert!(rendered.contains(" - Permissions from the root down:\n   "));
+        let chain = AncestorChain::new(&path);
+        for ancestor in chain.ancestors() {
+            let listing = if cfg!(unix) {
+                let (owner, group) = ancestor.owner_and_group().unwrap();
+                format!(
+                    "{} ",
+                    ancestor_chain::listing(ancestor.mode().unwrap(), &owner, &group)
+                )
+            } else {
+                String::new()
+            };
+            assert!(rendered.contains(&format!(
+                "   {listing}`{}` ({})\n",
+                ancestor.path().display(),
+                style::perms(&ancestor.permissions().unwrap())
+            )));
+        }
+        assert!(rendered.contains(&format!(
+            "`{}` (✅ read, ✅ write, ❌ execute)\n",
+            path.display()
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_name_too_long_shows_lengths() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let name = "é".repeat(200);
+        let path = tempdir.path().join(&name);
+        let error = std::fs::read(&path).unwrap_err();
+        assert_eq!(Some(libc::ENAMETOOLONG), error.raw_os_error());
+
+        let facts = PathFacts::with_error(&path, &error);
+        assert!(facts
+            .to_string()
+            .contains(&format!(" - Longest component is 400 bytes `{name}`\n")));
+    }
+
+    #[test]
+    fn test_contradiction_not_found_but_exists() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        let error = std::fs::read(&path).unwrap_err();
+        std::fs::write(&path, "").unwrap();
+
+        let facts = PathFacts::with_error(&path, &error);
+        assert_eq!(vec![Contradiction::ExistsNow], facts.contradictions());
+        let rendered = facts.to_string();
+        assert_eq!(
+            Some(" - Contradiction: the error said the path was not found, but it exists now, so it was probably created after the failure"),
+            rendered.lines().nth(1)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_symlink_is_not_a_contradiction() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let target = tempdir.path().join("missing.txt");
+        let link = tempdir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let error = std::fs::read(&link).unwrap_err();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+
+        let facts = PathFacts::with_error(&link, &error);
+        assert!(facts.contradictions().is_empty());
+        let rendered = facts.to_string();
+        assert!(!rendered.contains("Contradiction"));
+        assert_eq!(
+            Some(
+                format!(
+                    " - Symlink `{}` points to `{}`, which does not exist",
+                    link.display(),
+                    target.display()
+                )
+                .as_str()
+            ),
+            rendered.lines().nth(1)
+        );
+    }
+
+    #[test]
+    fn test_contradiction_already_exists_but_missing() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("dir");
+        let error = std::fs::create_dir(tempdir.path()).unwrap_err();
+
+        let facts = PathFacts::with_error(&path, &error);
+        assert_eq!(vec![Contradiction::MissingNow], facts.contradictions());
+
+        let facts = PathFacts::with_error(&path, &std::io::Error::from(ErrorKind::NotFound));
+        assert!(facts.contradictions().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! Drop-in replacements for `std::fs` that add path facts to errors
+//!
+//! Functions have the same signatures as their `std::fs` counterparts. Errors keep their
+//! original `ErrorKind`, and their message includes facts about every path involved. The
+//! underlying `FsError` can be retrieved with `get_ref` and `downcast_ref`.
+//!
+//! ```no_run
+//! use path_facts::fs;
+//!
+//! let contents = fs::read_to_string("doesnotexist.txt").unwrap();
+//! ```
+use crate::FsError;
+use std::path::{Path, PathBuf};
+
+pub use std::fs::{DirBuilder, DirEntry, FileType, Metadata, OpenOptions, Permissions, ReadDir};
+
+fn with_facts<T>(result: std::io::Result<T>, operation: &str, path: &Path) -> std::io::Result<T> {
+    result.map_err(|error| FsError::new(operation, error, path).into_io_error())
+}
+
+fn with_pair_facts<T>(
+    result: std::io::Result<T>,
+    operation: &str,
+    from: &Path,
+    to: &Path,
+) -> std::io::Result<T> {
+    result.map_err(|error| FsError::with_paths(operation, error, [from, to]).into_io_error())
+}
+
+/// Like `std::fs::read`
+pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    with_facts(std::fs::read(path), "read", path)
+}
+
+/// Like `std::fs::read_to_string`
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> std::io::Result<String> {
+    let path = path.as_ref();
+    with_facts(std::fs::read_to_string(path), "read", path)
+}
+
+/// Like `std::fs::write`
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(std::fs::write(path, contents), "write", path)
+}
+
+/// Like `std::fs::copy`
+pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> std::io::Result<u64> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    with_pair_facts(std::fs::copy(from, to), "copy", from, to)
+}
+
+/// Like `std::fs::rename`
+pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> std::io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    with_pair_facts(std::fs::rename(from, to), "rename", from, to)
+}
+
+/// Like `std::fs::hard_link`
+pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> std::io::Result<()> {
+    let (original, link) = (original.as_ref(), link.as_ref());
+    with_pair_facts(
+        std::fs::hard_link(original, link),
+        "hard link",
+        original,
+        link,
+    )
+}
+
+/// Like `std::fs::create_dir`
+pub fn create_dir<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(std::fs::create_dir(path), "create directory", path)
+}
+
+/// Like `std::fs::create_dir_all`
+pub fn create_dir_all<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(std::fs::create_dir_all(path), "create directory", path)
+}
+
+/// Like `std::fs::remove_file`
+pub fn remove_file<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(std::fs::remove_file(path), "remove file", path)
+}
+
+/// Like `std::fs::remove_dir`
+pub fn remove_dir<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(std::fs::remove_dir(path), "remove directory", path)
+}
+
+/// Like `std::fs::remove_dir_all`
+pub fn remove_dir_all<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(std::fs::remove_dir_all(path), "remove directory", path)
+}
+
+/// Like `std::fs::metadata`
+pub fn metadata<P: AsRef<Path>>(path: P) -> std::io::Result<Metadata> {
+    let path = path.as_ref();
+    with_facts(std::fs::metadata(path), "read metadata of", path)
+}
+
+/// Like `std::fs::symlink_metadata`
+pub fn symlink_metadata<P: AsRef<Path>>(path: P) -> std::io::Result<Metadata> {
+    let path = path.as_ref();
+    with_facts(std::fs::symlink_metadata(path), "read metadata of", path)
+}
+
+/// Like `std::fs::read_dir`
+pub fn read_dir<P: AsRef<Path>>(path: P) -> std::io::Result<ReadDir> {
+    let path = path.as_ref();
+    with_facts(std::fs::read_dir(path), "read directory", path)
+}
+
+/// Like `std::fs::read_link`
+pub fn read_link<P: AsRef<Path>>(path: P) -> std::io::Result<PathBuf> {
+    let path = path.as_ref();
+    with_facts(std::fs::read_link(path), "read link", path)
+}
+
+/// Like `std::fs::canonicalize`
+pub fn canonicalize<P: AsRef<Path>>(path: P) -> std::io::Result<PathBuf> {
+    let path = path.as_ref();
+    with_facts(std::fs::canonicalize(path), "canonicalize", path)
+}
+
+/// Like `std::fs::set_permissions`
+pub fn set_permissions<P: AsRef<Path>>(path: P, perm: Permissions) -> std::io::Result<()> {
+    let path = path.as_ref();
+    with_facts(
+        std::fs::set_permissions(path, perm),
+        "set permissions of",
+        path,
+    )
+}
+
+/// `open` and `create` like `std::fs::File`, adding path facts to errors
+///
+/// Both return a plain `std::fs::File`, so the result can be stored and passed anywhere
+/// `std::fs::File` is expected. This type is never constructed.
+#[derive(Debug)]
+pub enum File {}
+
+impl File {
+    /// Like `std::fs::File::open`
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<std::fs::File> {
+        let path = path.as_ref();
+        with_facts(std::fs::File::open(path), "open", path)
+    }
+
+    /// Like `std::fs::File::create`
+    pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<std::fs::File> {
+        let path = path.as_ref();
+        with_facts(std::fs::File::create(path), "create", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    fn fs_error(error: &std::io::Error) -> &FsError {
+        error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<FsError>())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_to_string_missing() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("does_not_exist.txt");
+
+        let error = read_to_string(&path).unwrap_err();
+        assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+        assert!(error
+            .to_string()
+            .contains("Missing `does_not_exist.txt` from parent directory"));
+        assert_eq!("read", fs_error(&error).operation());
+    }
+
+    #[test]
+    fn test_rename_has_facts_for_both_paths() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let from = tempdir.path().join("from.txt");
+        let to = tempdir.path().join("missing").join("to.txt");
+        write(&from, "").unwrap();
+
+        let error = rename(&from, &to).unwrap_err();
+        assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+        let facts = fs_error(&error).facts();
+        assert_eq!(
+            vec![from.as_path(), to.as_path()],
+            facts.iter().map(|f| f.path()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_file_open_and_create() {
+        use std::io::{Read, Write};
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        assert!(File::open(&path).is_err());
+
+        let mut file: std::fs::File = File::create(&path).unwrap();
+        file.write_all(b"hello").unwrap();
+        assert_eq!(5, file.metadata().unwrap().len());
+
+        let mut contents = String::new();
+        File::open(&path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!("hello", contents);
+    }
+}
@@ -0,0 +1,229 @@
+//! An `std::io::Error` paired with facts about the paths involved
+//!
+//! Keeps the original error as a typed source so callers can still match on its
+//! `ErrorKind` after adopting path facts.
+use crate::PathFacts;
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// A failed filesystem operation along with facts about every path it involved
+///
+/// ```no_run
+/// use path_facts::FsError;
+///
+/// let path = std::path::Path::new("doesnotexist.txt");
+/// let contents = std::fs::read_to_string(path)
+///     .map_err(|error| FsError::new("read", error, path))
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FsError {
+    operation: String,
+    error: std::io::Error,
+    paths: Vec<PathBuf>,
+    facts: Vec<PathFacts>,
+}
+
+impl FsError {
+    /// Gathers facts about a single path involved in a failed operation
+    pub fn new(
+        operation: impl Into<String>,
+        error: std::io::Error,
+        path: impl AsRef<Path>,
+    ) -> Self {
+        Self::with_paths(operation, error, [path])
+    }
+
+    /// Gathers facts about every path involved in a failed operation, such as the
+    /// `from` and `to` of a rename
+    ///
+    /// The error can't say which path caused it. A rename fails with `NotFound` when the
+    /// parent of `to` is missing, even though `from` exists. When the facts of some paths
+    /// agree with the error, it is attributed to them and the other paths are not reported
+    /// as contradicting it.
+    pub fn with_paths<P: AsRef<Path>>(
+        operation: impl Into<String>,
+        error: std::io::Error,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Self {
+        let paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        let mut facts = paths
+            .iter()
+            .map(|path| PathFacts::with_error(path, &error))
+            .collect::<Vec<_>>();
+        if facts.iter().any(|facts| facts.contradictions().is_empty()) {
+            for facts in &mut facts {
+                if !facts.contradictions().is_empty() {
+                    if let Some(error) = &mut facts.reported_error {
+                        error.unattributed = true;
+                    }
+                }
+            }
+        }
+        FsError {
+            operation: operation.into(),
+            error,
+            paths,
+            facts,
+        }
+    }
+
+    /// Name of the operation that failed
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// Kind of the original error
+    pub fn kind(&self) -> ErrorKind {
+        self.error.kind()
+    }
+
+    /// The original error
+    pub fn io_error(&self) -> &std::io::Error {
+        &self.error
+    }
+
+    /// Facts about each path, in the order they were given
+    pub fn facts(&self) -> &[PathFacts] {
+        &self.facts
+    }
+
+    /// Converts into an `std::io::Error` with the original kind and the facts in its message
+    ///
+    /// The `FsError` is preserved and can be retrieved with `get_ref` and `downcast_ref`.
+    pub fn into_io_error(self) -> std::io::Error {
+        std::io::Error::new(self.kind(), self)
+    }
+}
+
+impl Display for FsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| format!("`{}`", path.display()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            f,
+            "cannot {operation} {paths}: {error}",
+            operation = self.operation,
+            error = self.error
+        )?;
+        for facts in &self.facts {
+            write!(f, "{facts}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<FsError> for std::io::Error {
+    fn from(error: FsError) -> Self {
+        error.into_io_error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::formatdoc;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+    use std::error::Error;
+
+    #[test]
+    fn test_keeps_kind_and_source() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("does_not_exist.txt");
+        let error = std::fs::read_to_string(&path)
+            .map_err(|error| FsError::new("read", error, &path))
+            .unwrap_err();
+
+        assert_eq!(ErrorKind::NotFound, error.kind());
+        assert_eq!("read", error.operation());
+        assert_eq!(1, error.facts().len());
+        let source = error.source().unwrap();
+        assert_eq!(error.io_error().to_string(), source.to_string());
+
+        let expected = formatdoc! {"
+            cannot read `/path/to/directory/does_not_exist.txt`: {source}
+            does not exist `/path/to/directory/does_not_exist.txt`
+             - First missing component `does_not_exist.txt` in `/path/to/directory`
+             - Missing `does_not_exist.txt` from parent directory:
+               `/path/to/directory`
+                  └── (empty)
+        "}
+        .replace(
+            "/path/to/directory",
+            format!("{}", tempdir.path().display()).as_str(),
+        );
+        assert_eq!(expected.trim(), error.to_string().trim());
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let from = tempdir.path().join("from.txt");
+        let to = tempdir.path().join("to.txt");
+        let error = std::fs::rename(&from, &to)
+            .map_err(|error| FsError::with_paths("rename", error, [&from, &to]))
+            .unwrap_err();
+        assert_eq!(2, error.facts().len());
+
+        let message = error.to_string();
+        let io_error: std::io::Error = error.into();
+        assert_eq!(ErrorKind::NotFound, io_error.kind());
+        assert_eq!(message, io_error.to_string());
+        assert!(io_error.to_string().contains("Missing `to.txt`"));
+
+        let inner = io_error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<FsError>())
+            .unwrap();
+        assert_eq!("rename", inner.operation());
+    }
+
+    #[test]
+    fn test_rename_into_missing_parent_is_not_a_contradiction() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let from = tempdir.path().join("from.txt");
+        let to = tempdir.path().join("missing").join("to.txt");
+        std::fs::write(&from, "").unwrap();
+
+        let error = std::fs::rename(&from, &to)
+            .map_err(|error| FsError::with_paths("rename", error, [&from, &to]))
+            .unwrap_err();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+        assert!(error
+            .facts()
+            .iter()
+            .all(|facts| facts.contradictions().is_empty()));
+        assert!(!error.to_string().contains("Contradiction"));
+        assert!(error
+            .to_string()
+            .contains("First missing component `missing`"));
+
+        // Every path disagreeing with the error is still a contradiction
+        let error = FsError::with_paths(
+            "rename",
+            std::io::Error::from(ErrorKind::NotFound),
+            [&from, &from],
+        );
+        assert!(error
+            .facts()
+            .iter()
+            .all(|facts| facts.contradictions() == [crate::Contradiction::ExistsNow]));
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
     abs_path::{self, AbsPath},
     canonical_path::CanonicalPath,
+    inode_flags::InodeFlags,
+    ownership::Ownership,
+    path_facts::PathFacts,
+    permissions::Permissions,
     resolved_metadata::{ResolvedMetadata, ResolvedType},
+    symlink_chain::SymlinkChain,
 };
-use faccess::{AccessMode, PathExt};
 use std::path::Path;
 
 #[derive(Debug)]
@@ -13,20 +17,20 @@ pub(crate) struct HappyPath {
     pub(crate) symlink_target: Option<AbsPath>,
     pub(crate) resolved_type: ResolvedType,
     pub(crate) parent: DirOk,
-    pub(crate) read: bool,
-    pub(crate) write: bool,
-    pub(crate) execute: bool,
+    pub(crate) permissions: Permissions,
+    pub(crate) ownership: Option<Ownership>,
+    pub(crate) flags: Option<InodeFlags>,
 }
 
+/// A directory that exists and whose entries could be listed
 #[derive(Debug, Clone)]
-pub(crate) struct DirOk {
+pub struct DirOk {
     pub(crate) absolute: AbsPath,
-    #[allow(dead_code)]
     pub(crate) canonical: CanonicalPath,
     pub(crate) entries: Vec<AbsPath>,
-    pub(crate) read: bool,
-    pub(crate) write: bool,
-    pub(crate) execute: bool,
+    pub(crate) permissions: Permissions,
+    pub(crate) ownership: Option<Ownership>,
+    pub(crate) flags: Option<InodeFlags>,
 }
 
 impl DirOk {
@@ -34,43 +38,114 @@ impl DirOk {
         let canonical = CanonicalPath::new(&absolute)?;
         let entries = absolute.read_dir()?;
 
-        let read = true;
-        let write = canonical.as_ref().access(AccessMode::WRITE).is_ok();
-        let execute = canonical.as_ref().access(AccessMode::EXECUTE).is_ok();
+        // Listing the entries succeeded so we know it's readable
+        let permissions = Permissions {
+            read: true,
+            ..Permissions::effective(canonical.as_ref())
+        };
+        let ownership = Ownership::new(canonical.as_ref());
+        let flags = InodeFlags::new(canonical.as_ref());
+        let permissions = restrict(permissions, flags.as_ref(), true);
 
         Ok(DirOk {
             absolute,
             canonical,
             entries,
-            read,
-            write,
-            execute,
+            permissions,
+            ownership,
+            flags,
         })
     }
 
     pub(crate) fn has_entry(&self, path: &AbsPath) -> bool {
         self.entries.contains(path)
     }
+
+    /// Absolute path of the directory
+    pub fn path(&self) -> &Path {
+        self.absolute.as_ref()
+    }
+
+    /// Canonical path of the directory
+    pub fn canonical(&self) -> &Path {
+        self.canonical.as_ref()
+    }
+
+    /// Absolute paths of the directory entries at the time it was read
+    pub fn entries(&self) -> impl Iterator<Item = &Path> {
+        self.entries.iter().map(AsRef::as_ref)
+    }
+
+    /// Effective permissions of the directory
+    pub fn permissions(&self) -> Permissions {
+        self.permissions
+    }
+
+    /// Owner, group, and mode bits of the directory (unix only)
+    pub fn ownership(&self) -> Option<&Ownership> {
+        self.ownership.as_ref()
+    }
+
+    /// Inode flags of the directory, `None` when none are set (Linux only)
+    pub fn flags(&self) -> Option<&InodeFlags> {
+        self.flags.as_ref()
+    }
 }
 
+/// A directory that can be searched (execute) but not listed (read)
+///
+/// Paths inside of it can still be looked up directly.
+#[derive(Debug, Clone)]
+pub(crate) struct SearchOnlyDir {
+    pub(crate) absolute: AbsPath,
+    pub(crate) permissions: Permissions,
+    pub(crate) flags: Option<InodeFlags>,
+}
+
+/// Facts about a path that was looked up directly instead of found by listing its parent
+#[derive(Debug)]
+pub(crate) struct LookedUp {
+    pub(crate) canonical: CanonicalPath,
+    pub(crate) symlink_target: Option<AbsPath>,
+    pub(crate) resolved_type: ResolvedType,
+    pub(crate) permissions: Permissions,
+    pub(crate) ownership: Option<Ownership>,
+    pub(crate) flags: Option<InodeFlags>,
+}
+
+#[derive(Debug)]
 pub(crate) enum UnhappyPath {
     AbsPathError(abs_path::AbsPathError),
     IsRoot(AbsPath),
     ParentProblem {
         absolute: AbsPath,
         parent: AbsPath,
-        #[allow(dead_code)]
         error: std::io::Error,
+        /// Ancestors above `parent` that also could not be accessed, closest first
+        ancestors: Vec<(AbsPath, std::io::Error)>,
+        /// Facts about the closest ancestor that does not have a parent problem
+        prior: Box<PathFacts>,
     },
     DoesNotExist {
         absolute: AbsPath,
         parent: DirOk,
     },
+    /// Parent can be searched but not listed, the path was looked up directly
+    ///
+    /// `target` is `None` when the path does not exist.
+    ParentNotListable {
+        absolute: AbsPath,
+        parent: SearchOnlyDir,
+        error: std::io::Error,
+        target: Option<LookedUp>,
+    },
     // Path exists, but we cannot canonicalize it
     CannotCanonicalize {
         absolute: AbsPath,
         parent: DirOk,
         error: std::io::Error,
+        /// Links followed from the path when it is a symlink
+        symlink_chain: Option<SymlinkChain>,
     },
     /// Path exists, but we cannot read the metadata
     /// Can happen if we have read access on the parent dir but not execute access (to view permissions)
@@ -95,11 +170,23 @@ pub(crate) fn state(path: &Path) -> Result<HappyPath, Box<UnhappyPath>> {
     let abs_parent = absolute
         .parent()
         .ok_or_else(|| UnhappyPath::IsRoot(absolute.clone()))?;
-    let parent = DirOk::new(abs_parent.clone()).map_err(|error| UnhappyPath::ParentProblem {
-        absolute: absolute.clone(),
-        parent: abs_parent.clone(),
-        error,
-    })?;
+    let parent = match DirOk::new(abs_parent.clone()) {
+        Ok(parent) => parent,
+        Err(error) => {
+            let error = match search_only(&absolute, &abs_parent, error) {
+                Ok(unlisted) => return Err(unlisted),
+                Err(error) => error,
+            };
+            let (ancestors, prior) = prior_facts(&abs_parent);
+            return Err(Box::new(UnhappyPath::ParentProblem {
+                absolute: absolute.clone(),
+                parent: abs_parent.clone(),
+                error,
+                ancestors,
+                prior: Box::new(prior),
+            }));
+        }
+    };
     let path_does_not_exist = !parent.has_entry(&absolute);
     let canonical = CanonicalPath::new(&absolute).map_err(|error| {
         if path_does_not_exist {
@@ -112,6 +199,10 @@ pub(crate) fn state(path: &Path) -> Result<HappyPath, Box<UnhappyPath>> {
                 absolute: absolute.clone(),
                 parent: parent.clone(),
                 error,
+                symlink_chain: absolute
+                    .as_ref()
+                    .is_symlink()
+                    .then(|| SymlinkChain::new(&absolute)),
             }
         }
     })?;
@@ -132,9 +223,13 @@ pub(crate) fn state(path: &Path) -> Result<HappyPath, Box<UnhappyPath>> {
             error,
         })?;
 
-    let read = canonical.as_ref().access(AccessMode::READ).is_ok();
-    let write = canonical.as_ref().access(AccessMode::WRITE).is_ok();
-    let execute = canonical.as_ref().access(AccessMode::EXECUTE).is_ok();
+    let ownership = Ownership::new(canonical.as_ref());
+    let flags = InodeFlags::new(canonical.as_ref());
+    let permissions = restrict(
+        Permissions::effective(canonical.as_ref()),
+        flags.as_ref(),
+        resolved_type == ResolvedType::Dir,
+    );
 
     Ok(HappyPath {
         absolute,
@@ -142,8 +237,108 @@ pub(crate) fn state(path: &Path) -> Result<HappyPath, Box<UnhappyPath>> {
         symlink_target,
         resolved_type,
         parent,
-        read,
-        write,
-        execute,
+        permissions,
+        ownership,
+        flags,
+    })
+}
+
+/// Looks up the path directly when the parent can be searched but not listed
+///
+/// Returns the original error when that's not the case so it can be reported as a parent problem.
+fn search_only(
+    absolute: &AbsPath,
+    abs_parent: &AbsPath,
+    error: std::io::Error,
+) -> Result<Box<UnhappyPath>, std::io::Error> {
+    if error.kind() != std::io::ErrorKind::PermissionDenied {
+        return Err(error);
+    }
+    let Ok(canonical) = CanonicalPath::new(abs_parent) else {
+        return Err(error);
+    };
+    let target = match std::fs::symlink_metadata(absolute) {
+        Ok(_) => match look_up(absolute) {
+            Ok(target) => Some(target),
+            Err(_) => return Err(error),
+        },
+        Err(lookup) if lookup.kind() == std::io::ErrorKind::NotFound => None,
+        Err(_) => return Err(error),
+    };
+    // Listing failed, so it's not readable regardless of what `access` says
+    let permissions = Permissions {
+        read: false,
+        ..Permissions::effective(canonical.as_ref())
+    };
+    let flags = InodeFlags::new(canonical.as_ref());
+    let permissions = restrict(permissions, flags.as_ref(), true);
+
+    Ok(Box::new(UnhappyPath::ParentNotListable {
+        absolute: absolute.clone(),
+        parent: SearchOnlyDir {
+            absolute: abs_parent.clone(),
+            permissions,
+            flags,
+        },
+        error,
+        target,
+    }))
+}
+
+fn look_up(absolute: &AbsPath) -> Result<LookedUp, std::io::Error> {
+    let canonical = CanonicalPath::new(absolute)?;
+    let resolved_type = ResolvedMetadata::new(absolute)?.resolved_type();
+    let symlink_target = abs_path::try_readlink(absolute)?;
+    let ownership = Ownership::new(canonical.as_ref());
+    let flags = InodeFlags::new(canonical.as_ref());
+    let permissions = restrict(
+        Permissions::effective(canonical.as_ref()),
+        flags.as_ref(),
+        resolved_type == ResolvedType::Dir,
+    );
+
+    Ok(LookedUp {
+        canonical,
+        symlink_target,
+        resolved_type,
+        permissions,
+        ownership,
+        flags,
     })
 }
+
+/// Inode flags such as immutable block writes even when `access` allows them
+fn restrict(permissions: Permissions, flags: Option<&InodeFlags>, is_dir: bool) -> Permissions {
+    match flags {
+        Some(flags) => flags.restrict(permissions, is_dir),
+        None => permissions,
+    }
+}
+
+/// Walks up the parent chain until it finds a directory that is not a `ParentProblem`
+///
+/// Returns the errors of every ancestor passed on the way, closest first. Gathered eagerly
+/// so that rendering facts does not touch the disk.
+fn prior_facts(dir: &AbsPath) -> (Vec<(AbsPath, std::io::Error)>, PathFacts) {
+    let state = match state(dir.as_ref()) {
+        Err(unhappy) => match *unhappy {
+            UnhappyPath::ParentProblem {
+                parent,
+                error,
+                mut ancestors,
+                prior,
+                ..
+            } => {
+                ancestors.insert(0, (parent, error));
+                return (ancestors, *prior);
+            }
+            other => Err(Box::new(other)),
+        },
+        ok => ok,
+    };
+
+    // The facts that hold this prior already show the mounts, capacity, and length limits
+    // of the closest existing path
+    let prior = PathFacts::from_state(dir.as_ref(), state);
+    (vec![], prior)
+}
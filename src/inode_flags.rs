@@ -0,0 +1,246 @@
+//! Inode flags such as immutable and append-only (Linux only)
+//!
+//! Flags set with `chattr` restrict a path beyond its mode bits, even for root. An append-only
+//! file passes an `access(W_OK)` check, but `std::fs::write` truncates it and fails with
+//! `EPERM`. Flags are read from `statx` attributes, which only need search permission on the
+//! parent, and from the `FS_IOC_GETFLAGS` ioctl for filesystems that don't report them.
+use crate::permissions::Permissions;
+use std::{fmt::Display, path::Path};
+
+// Values shared by `FS_*_FL` inode flags and `STATX_ATTR_*` attributes
+const COMPRESSED: u32 = 0x0000_0004;
+const IMMUTABLE: u32 = 0x0000_0010;
+const APPEND: u32 = 0x0000_0020;
+const NODUMP: u32 = 0x0000_0040;
+const ENCRYPTED: u32 = 0x0000_0800;
+const VERITY: u32 = 0x0010_0000;
+/// `STATX_ATTR_DAX`
+const STATX_DAX: u32 = 0x0020_0000;
+/// `FS_DAX_FL`, different from the statx value
+const FS_DAX: u32 = 0x0200_0000;
+
+/// Inode flags that change what can be done with a path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InodeFlags {
+    pub(crate) immutable: bool,
+    pub(crate) append_only: bool,
+    pub(crate) encrypted: bool,
+    pub(crate) verity: bool,
+    pub(crate) compressed: bool,
+    pub(crate) dax: bool,
+    pub(crate) nodump: bool,
+}
+
+impl InodeFlags {
+    /// Reads the flags of the path, following symlinks
+    ///
+    /// Returns `None` when no flags are set or they cannot be read.
+    pub(crate) fn new(path: &Path) -> Option<Self> {
+        let statx = statx_attributes(path).unwrap_or_default();
+        let ioctl = ioctl_flags(path).unwrap_or_default();
+        let flags = InodeFlags {
+            immutable: (statx | ioctl) & IMMUTABLE != 0,
+            append_only: (statx | ioctl) & APPEND != 0,
+            encrypted: (statx | ioctl) & ENCRYPTED != 0,
+            verity: (statx | ioctl) & VERITY != 0,
+            compressed: (statx | ioctl) & COMPRESSED != 0,
+            dax: statx & STATX_DAX != 0 || ioctl & FS_DAX != 0,
+            nodump: (statx | ioctl) & NODUMP != 0,
+        };
+        (flags != InodeFlags::default()).then_some(flags)
+    }
+
+    /// Cannot be modified, renamed, deleted, or linked to, even by root (`chattr +i`)
+    pub fn immutable(&self) -> bool {
+        self.immutable
+    }
+
+    /// Files can only be opened for appending, directory entries cannot be removed (`chattr +a`)
+    pub fn append_only(&self) -> bool {
+        self.append_only
+    }
+
+    /// Encrypted with fscrypt, access fails with `ENOKEY` while the key is locked
+    pub fn encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// Protected by fs-verity, contents are read-only
+    pub fn verity(&self) -> bool {
+        self.verity
+    }
+
+    pub fn compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Accessed directly instead of through the page cache
+    pub fn dax(&self) -> bool {
+        self.dax
+    }
+
+    /// Skipped by `dump` backups
+    pub fn nodump(&self) -> bool {
+        self.nodump
+    }
+
+    /// Takes away permissions that the flags block, regardless of what `access` says
+    pub(crate) fn restrict(&self, permissions: Permissions, is_dir: bool) -> Permissions {
+        Permissions {
+            write: permissions.write && self.write_blocker(is_dir).is_none(),
+            ..permissions
+        }
+    }
+
+    /// The flag that blocks writing, if any
+    ///
+    /// An append-only directory can still get new entries, so it only blocks writing files.
+    pub(crate) fn write_blocker(&self, is_dir: bool) -> Option<&'static str> {
+        if self.immutable {
+            Some("immutable")
+        } else if self.append_only && !is_dir {
+            Some("append-only")
+        } else if self.verity && !is_dir {
+            Some("fs-verity")
+        } else {
+            None
+        }
+    }
+
+    /// Each set flag and what it means
+    pub(crate) fn descriptions(&self, is_dir: bool) -> Vec<&'static str> {
+        [
+            (
+                self.immutable,
+                "immutable (cannot be modified, renamed, deleted, or linked to, even by root, `chattr -i` clears it)",
+            ),
+            (
+                self.append_only,
+                if is_dir {
+                    "append-only (entries can be created but not deleted or renamed, even by root)"
+                } else {
+                    "append-only (can only be opened for appending, writing, truncating, renaming, and deleting fail with EPERM, even for root)"
+                },
+            ),
+            (
+                self.encrypted,
+                "encrypted (fscrypt, names and contents fail with ENOKEY while the key is locked)",
+            ),
+            (self.verity, "fs-verity (contents are read-only and verified on read)"),
+            (self.compressed, "compressed"),
+            (self.dax, "DAX (accessed directly instead of through the page cache)"),
+            (self.nodump, "nodump (skipped by `dump` backups)"),
+        ]
+        .into_iter()
+        .filter_map(|(set, description)| set.then_some(description))
+        .collect()
+    }
+}
+
+impl Display for InodeFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (self.immutable, "immutable"),
+            (self.append_only, "append-only"),
+            (self.encrypted, "encrypted"),
+            (self.verity, "fs-verity"),
+            (self.compressed, "compressed"),
+            (self.dax, "DAX"),
+            (self.nodump, "nodump"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect::<Vec<_>>();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// Attributes from `statx` that the filesystem supports
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn statx_attributes(path: &Path) -> Option<u32> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statx` is plain data, zeroed is a valid value
+    let mut statx: libc::statx = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is nul terminated and `statx` is a valid buffer
+    let result = unsafe { libc::statx(libc::AT_FDCWD, path.as_ptr(), 0, 0, &mut statx) };
+    (result == 0).then_some((statx.stx_attributes & statx.stx_attributes_mask) as u32)
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn statx_attributes(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Flags from the `FS_IOC_GETFLAGS` ioctl, needs read permission to open the path
+///
+/// Only regular files and directories are opened, opening devices or FIFOs has side effects.
+#[cfg(target_os = "linux")]
+fn ioctl_flags(path: &Path) -> Option<u32> {
+    use std::os::{fd::AsRawFd, unix::fs::OpenOptionsExt};
+
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() && !metadata.is_dir() {
+        return None;
+    }
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOCTTY)
+        .open(path)
+        .ok()?;
+    let mut flags: libc::c_long = 0;
+    // SAFETY: `FS_IOC_GETFLAGS` writes an int sized value into `flags`, which is larger
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+    (result == 0).then_some(flags as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn ioctl_flags(_path: &Path) -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_restrict_and_describe() {
+        let flags = InodeFlags {
+            append_only: true,
+            nodump: true,
+            ..InodeFlags::default()
+        };
+        let all = Permissions::new(true, true, true);
+        assert_eq!(
+            Permissions::new(true, false, true),
+            flags.restrict(all, false)
+        );
+        assert_eq!(all, flags.restrict(all, true));
+        assert_eq!("append-only, nodump", flags.to_string());
+        assert_eq!(
+            vec![
+                "append-only (entries can be created but not deleted or renamed, even by root)",
+                "nodump (skipped by `dump` backups)"
+            ],
+            flags.descriptions(true)
+        );
+
+        let immutable = InodeFlags {
+            immutable: true,
+            ..InodeFlags::default()
+        };
+        assert_eq!(Some("immutable"), immutable.write_blocker(true));
+    }
+
+    #[test]
+    fn test_no_flags() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(None, InodeFlags::new(&path));
+    }
+}
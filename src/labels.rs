@@ -0,0 +1,466 @@
+//! Extended attributes and mandatory access control labels
+//!
+//! On hardened hosts a permission error often comes from SELinux or AppArmor rather than
+//! mode bits. `faccessat` and mode bits don't show that, the labels of the path and the
+//! process do. Gathering them is opt-in because it lists every extended attribute.
+use crate::{path_facts::PathFacts, style, xattr};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// Extended attribute that holds the SELinux context of a path
+const SELINUX: &str = "security.selinux";
+
+/// File types that confined SELinux domains are commonly denied, and where they come from
+const COMMONLY_DENIED: &[(&str, &str)] = &[
+    ("user_home_t", "a home directory"),
+    ("user_home_dir_t", "a home directory"),
+    ("admin_home_t", "root's home directory"),
+    ("user_tmp_t", "a user's temp files"),
+    ("default_t", "a path without a matching file context rule"),
+    ("unlabeled_t", "a filesystem that was never labeled"),
+    ("file_t", "a filesystem that was never labeled"),
+];
+
+/// An SELinux security context, such as `system_u:object_r:httpd_sys_content_t:s0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelinuxContext {
+    pub(crate) user: String,
+    pub(crate) role: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub(crate) kind: String,
+    /// MLS/MCS level such as `s0:c1,c2`, when the policy uses one
+    pub(crate) level: Option<String>,
+}
+
+impl SelinuxContext {
+    /// Parses `user:role:type[:level]`, the level itself can contain colons
+    pub(crate) fn parse(label: &str) -> Option<Self> {
+        let label = label.trim_end_matches(['\0', '\n']);
+        let mut fields = label.splitn(4, ':');
+        let user = fields.next()?;
+        let role = fields.next()?;
+        let kind = fields.next()?;
+        if [user, role, kind].iter().any(|field| field.is_empty()) {
+            return None;
+        }
+        Some(SelinuxContext {
+            user: user.to_string(),
+            role: role.to_string(),
+            kind: kind.to_string(),
+            level: fields.next().map(String::from),
+        })
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    /// The type, which is what most policy rules are written against
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn level(&self) -> Option<&str> {
+        self.level.as_deref()
+    }
+}
+
+impl Display for SelinuxContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.user, self.role, self.kind)?;
+        if let Some(level) = &self.level {
+            write!(f, ":{level}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Security module whose label is in `/proc/self/attr/current`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lsm {
+    Selinux,
+    AppArmor,
+}
+
+impl Lsm {
+    /// Finds the labeling module in a comma separated list such as `/sys/kernel/security/lsm`
+    ///
+    /// Only one of them can be active at a time.
+    fn from_list(list: &str) -> Option<Self> {
+        list.trim().split(',').find_map(|name| match name {
+            "selinux" => Some(Lsm::Selinux),
+            "apparmor" => Some(Lsm::AppArmor),
+            _ => None,
+        })
+    }
+}
+
+/// The label of the current process from `/proc/self/attr`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum ProcessLabel {
+    Selinux {
+        context: SelinuxContext,
+    },
+    /// An AppArmor profile such as `docker-default (enforce)`, or `unconfined`
+    AppArmor {
+        profile: String,
+        mode: Option<String>,
+    },
+}
+
+impl ProcessLabel {
+    /// Reads the label of this process, parsed by the module that wrote it
+    ///
+    /// AppArmor has its own `/proc/self/attr/apparmor/current` since Linux 5.8. Otherwise
+    /// `/sys/kernel/security/lsm` says whether `/proc/self/attr/current` holds an SELinux
+    /// context or an AppArmor profile, their shapes can't tell them apart. AppArmor profiles
+    /// are often named after paths, which can contain colons.
+    fn current() -> Option<Self> {
+        if let Ok(label) = std::fs::read_to_string("/proc/self/attr/apparmor/current") {
+            return ProcessLabel::parse(Lsm::AppArmor, &label);
+        }
+        let lsm = std::fs::read_to_string("/sys/kernel/security/lsm")
+            .ok()
+            .and_then(|list| Lsm::from_list(&list))?;
+        std::fs::read_to_string("/proc/self/attr/current")
+            .ok()
+            .and_then(|label| ProcessLabel::parse(lsm, &label))
+    }
+
+    fn parse(lsm: Lsm, label: &str) -> Option<Self> {
+        let label = label.trim_end_matches(['\0', '\n']).trim();
+        if label.is_empty() {
+            return None;
+        }
+        match lsm {
+            Lsm::Selinux => {
+                SelinuxContext::parse(label).map(|context| ProcessLabel::Selinux { context })
+            }
+            Lsm::AppArmor => {
+                let (profile, mode) = match label.rsplit_once(" (") {
+                    Some((profile, mode)) => {
+                        (profile, Some(mode.trim_end_matches(')').to_string()))
+                    }
+                    None => (label, None),
+                };
+                Some(ProcessLabel::AppArmor {
+                    profile: profile.to_string(),
+                    mode,
+                })
+            }
+        }
+    }
+
+    /// True when the process runs in an SELinux domain or AppArmor profile that restricts it
+    pub fn is_confined(&self) -> bool {
+        match self {
+            ProcessLabel::Selinux { context } => !context.kind.starts_with("unconfined_"),
+            ProcessLabel::AppArmor { profile, mode } => {
+                profile != "unconfined" && mode.as_deref() != Some("unconfined")
+            }
+        }
+    }
+}
+
+impl Display for ProcessLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessLabel::Selinux { context } => write!(f, "SELinux `{context}`"),
+            ProcessLabel::AppArmor {
+                profile,
+                mode: Some(mode),
+            } => write!(f, "AppArmor `{profile}` ({mode})"),
+            ProcessLabel::AppArmor {
+                profile,
+                mode: None,
+            } => write!(f, "AppArmor `{profile}`"),
+        }
+    }
+}
+
+/// Extended attributes and SELinux context of a single path
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathLabels {
+    pub(crate) path: PathBuf,
+    /// Attribute names and value sizes in bytes, `None` when they could not be listed
+    pub(crate) xattrs: Option<Vec<(String, usize)>>,
+    pub(crate) selinux: Option<SelinuxContext>,
+}
+
+impl PathLabels {
+    fn new(path: &Path) -> Self {
+        let selinux = xattr::get(path, SELINUX)
+            .ok()
+            .flatten()
+            .and_then(|value| SelinuxContext::parse(&String::from_utf8_lossy(&value)));
+        PathLabels {
+            path: path.to_path_buf(),
+            xattrs: xattr::list(path).ok(),
+            selinux,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Extended attribute names and value sizes in bytes, `None` when they could not be listed
+    pub fn xattrs(&self) -> Option<&[(String, usize)]> {
+        self.xattrs.as_deref()
+    }
+
+    /// SELinux context from the `security.selinux` attribute
+    pub fn selinux(&self) -> Option<&SelinuxContext> {
+        self.selinux.as_ref()
+    }
+}
+
+/// Labels of a path, its parent, and the current process
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelFacts {
+    pub(crate) target: Option<PathLabels>,
+    pub(crate) parent: Option<PathLabels>,
+    pub(crate) process: Option<ProcessLabel>,
+}
+
+impl LabelFacts {
+    pub(crate) fn new(facts: &PathFacts) -> Self {
+        let absolute = facts.absolute();
+        LabelFacts {
+            target: absolute.filter(|path| path.exists()).map(PathLabels::new),
+            parent: absolute.and_then(Path::parent).map(PathLabels::new),
+            process: ProcessLabel::current(),
+        }
+    }
+
+    /// Labels of the path, `None` when it does not exist
+    pub fn target(&self) -> Option<&PathLabels> {
+        self.target.as_ref()
+    }
+
+    pub fn parent(&self) -> Option<&PathLabels> {
+        self.parent.as_ref()
+    }
+
+    /// Label of the process that gathered the facts
+    pub fn process(&self) -> Option<&ProcessLabel> {
+        self.process.as_ref()
+    }
+
+    /// Label combinations that commonly cause denials
+    ///
+    /// Such as a file labeled `user_home_t` and a process confined to `httpd_t`.
+    pub fn warnings(&self) -> Vec<String> {
+        let Some(process) = self.process.as_ref().filter(|label| label.is_confined()) else {
+            return vec![];
+        };
+        let mut warnings = vec![];
+        if let ProcessLabel::Selinux { context: domain } = process {
+            for labels in [&self.target, &self.parent].into_iter().flatten() {
+                let Some(context) = &labels.selinux else {
+                    continue;
+                };
+                if let Some((kind, origin)) = COMMONLY_DENIED
+                    .iter()
+                    .find(|(kind, _)| *kind == context.kind)
+                {
+                    warnings.push(format!(
+                        "`{path}` is labeled `{kind}`, usually from {origin}, which confined domains such as `{domain}` are commonly denied. Compare with `matchpathcon` and relabel with `restorecon`",
+                        path = labels.path.display(),
+                        domain = domain.kind,
+                    ));
+                }
+            }
+        }
+        if let ProcessLabel::AppArmor { profile, mode } = process {
+            if mode.as_deref() == Some("enforce") {
+                warnings.push(format!(
+                    "the process is confined by AppArmor profile `{profile}`, which can deny access regardless of mode bits. Check the kernel log for `apparmor=\"DENIED\"`"
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+fn fmt_path_labels(name: &str, labels: &PathLabels) -> String {
+    let path = labels.path.display();
+    let xattrs = match &labels.xattrs {
+        None => "extended attributes cannot be listed".to_string(),
+        Some(xattrs) if xattrs.is_empty() => "no extended attributes".to_string(),
+        Some(xattrs) => xattrs
+            .iter()
+            .map(|(name, size)| format!("`{name}` ({size} bytes)"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    let selinux = labels
+        .selinux
+        .as_ref()
+        .map(|context| format!(", SELinux `{context}`"))
+        .unwrap_or_default();
+    format!("{name}`{path}`: {xattrs}{selinux}")
+}
+
+impl Display for LabelFacts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Labels:")?;
+        match &self.process {
+            Some(process) => writeln!(f, "{}", style::bullet(format!("process: {process}")))?,
+            None => writeln!(
+                f,
+                "{}",
+                style::bullet("process: no SELinux or AppArmor label")
+            )?,
+        }
+        if let Some(target) = &self.target {
+            writeln!(f, "{}", style::bullet(fmt_path_labels("", target)))?;
+        }
+        if let Some(parent) = &self.parent {
+            writeln!(f, "{}", style::bullet(fmt_path_labels("parent ", parent)))?;
+        }
+        for warning in self.warnings() {
+            writeln!(f, "{}", style::bullet(format!("Warning: {warning}")))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_parse_labels() {
+        let context =
+            SelinuxContext::parse("system_u:object_r:httpd_sys_content_t:s0:c1,c2\0").unwrap();
+        assert_eq!("httpd_sys_content_t", context.kind());
+        assert_eq!(Some("s0:c1,c2"), context.level());
+        assert_eq!(
+            "system_u:object_r:httpd_sys_content_t:s0:c1,c2",
+            context.to_string()
+        );
+        assert_eq!(None, SelinuxContext::parse("docker-default (enforce)"));
+
+        let apparmor = ProcessLabel::parse(Lsm::AppArmor, "docker-default (enforce)\n").unwrap();
+        assert_eq!(
+            ProcessLabel::AppArmor {
+                profile: "docker-default".to_string(),
+                mode: Some("enforce".to_string())
+            },
+            apparmor
+        );
+        assert!(apparmor.is_confined());
+        assert!(!ProcessLabel::parse(Lsm::AppArmor, "unconfined\n")
+            .unwrap()
+            .is_confined());
+        assert!(!ProcessLabel::parse(
+            Lsm::Selinux,
+            "unconfined_u:unconfined_r:unconfined_t:s0-s0:c0.c1023"
+        )
+        .unwrap()
+        .is_confined());
+        assert_eq!(None, ProcessLabel::parse(Lsm::AppArmor, "\n"));
+    }
+
+    #[test]
+    fn test_parser_follows_active_module() {
+        assert_eq!(
+            Some(Lsm::AppArmor),
+            Lsm::from_list("lockdown,capability,landlock,yama,apparmor\n")
+        );
+        assert_eq!(
+            Some(Lsm::Selinux),
+            Lsm::from_list("capability,yama,selinux,bpf")
+        );
+        assert_eq!(None, Lsm::from_list("capability,landlock,yama"));
+
+        // A profile named after a path with colons is not an SELinux context
+        assert_eq!(
+            Some(ProcessLabel::AppArmor {
+                profile: "/opt/app:v2:bin/server".to_string(),
+                mode: Some("complain".to_string())
+            }),
+            ProcessLabel::parse(Lsm::AppArmor, "/opt/app:v2:bin/server (complain)\n")
+        );
+        assert_eq!(
+            None,
+            ProcessLabel::parse(Lsm::Selinux, "docker-default (enforce)")
+        );
+    }
+
+    #[test]
+    fn test_user_home_t_for_confined_service() {
+        let labels = LabelFacts {
+            target: Some(PathLabels {
+                path: PathBuf::from("/home/app/index.html"),
+                xattrs: Some(vec![("security.selinux".to_string(), 37)]),
+                selinux: SelinuxContext::parse("unconfined_u:object_r:user_home_t:s0"),
+            }),
+            parent: Some(PathLabels {
+                path: PathBuf::from("/home/app"),
+                xattrs: Some(vec![]),
+                selinux: None,
+            }),
+            process: ProcessLabel::parse(Lsm::Selinux, "system_u:system_r:httpd_t:s0"),
+        };
+        assert_eq!(
+            indoc::indoc! {"
+                Labels:
+                 - process: SELinux `system_u:system_r:httpd_t:s0`
+                 - `/home/app/index.html`: `security.selinux` (37 bytes), SELinux `unconfined_u:object_r:user_home_t:s0`
+                 - parent `/home/app`: no extended attributes
+                 - Warning: `/home/app/index.html` is labeled `user_home_t`, usually from a home directory, which confined domains such as `httpd_t` are commonly denied. Compare with `matchpathcon` and relabel with `restorecon`
+            "},
+            labels.to_string()
+        );
+
+        let unconfined = LabelFacts {
+            process: ProcessLabel::parse(Lsm::Selinux, "unconfined_u:unconfined_r:unconfined_t:s0"),
+            ..labels
+        };
+        assert!(unconfined.warnings().is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_lists_xattrs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        if !xattr::set(&path, "user.origin", b"build") {
+            // Filesystem without user xattr support
+            return;
+        }
+
+        let facts = PathFacts::new(&path).labels();
+        let labels = facts.label_facts().unwrap();
+        assert_eq!(
+            Some(&[("user.origin".to_string(), 5)][..]),
+            labels.target().unwrap().xattrs()
+        );
+        assert_eq!(tempdir.path(), labels.parent().unwrap().path());
+        assert!(facts.to_string().contains(" - Labels:\n    - process: "));
+        assert!(facts.to_string().contains(&format!(
+            "    - `{}`: `user.origin` (5 bytes)\n",
+            path.display()
+        )));
+    }
+}
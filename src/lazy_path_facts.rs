@@ -0,0 +1,112 @@
+//! Defers gathering facts until they're needed
+//!
+//! Facts don't come cheap, gathering them makes system calls. Errors are often created
+//! and then handled without ever being displayed, so storing only the path keeps the
+//! error path fast.
+use crate::PathFacts;
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::SystemTime,
+};
+
+/// Holds a path and gathers `PathFacts` the first time they are displayed or queried
+///
+/// The result is memoized so every later use sees the same facts. Because facts are
+/// gathered after the failure, the disk might have changed in between. The moment of
+/// failure is recorded and available via `failed_at`.
+///
+/// ```no_run
+/// use path_facts::LazyPathFacts;
+///
+/// struct ReadError {
+///     error: std::io::Error,
+///     facts: LazyPathFacts,
+/// }
+///
+/// let path = std::path::Path::new("doesnotexist.txt");
+/// let result = std::fs::read_to_string(path).map_err(|error| ReadError {
+///     error,
+///     facts: LazyPathFacts::new(path),
+/// });
+///
+/// if let Err(ReadError { error, facts }) = result {
+///     eprintln!("{error}. {facts}");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LazyPathFacts {
+    path: PathBuf,
+    failed_at: SystemTime,
+    facts: OnceLock<PathFacts>,
+}
+
+impl LazyPathFacts {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        LazyPathFacts {
+            path: path.as_ref().to_owned(),
+            failed_at: SystemTime::now(),
+            facts: OnceLock::new(),
+        }
+    }
+
+    /// The path as it was given
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// When the `LazyPathFacts` was created, which is usually the moment of failure
+    pub fn failed_at(&self) -> SystemTime {
+        self.failed_at
+    }
+
+    /// Gathers facts on the first call and returns the same facts afterwards
+    pub fn facts(&self) -> &PathFacts {
+        self.facts.get_or_init(|| PathFacts::new(&self.path))
+    }
+}
+
+impl From<LazyPathFacts> for PathFacts {
+    fn from(lazy: LazyPathFacts) -> Self {
+        let LazyPathFacts { path, facts, .. } = lazy;
+        facts.into_inner().unwrap_or_else(|| PathFacts::new(path))
+    }
+}
+
+impl Display for LazyPathFacts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.facts())
+    }
+}
+
+impl PathFacts {
+    /// Stores the path and defers gathering facts until they're displayed or queried
+    pub fn deferred(path: impl AsRef<Path>) -> LazyPathFacts {
+        LazyPathFacts::new(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathState;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_gathers_on_first_use_then_memoizes() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+
+        let lazy = PathFacts::deferred(&path);
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(PathState::Exists, lazy.facts().state());
+        let rendered = lazy.to_string();
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(PathState::Exists, lazy.facts().state());
+        assert_eq!(rendered, lazy.to_string());
+        assert_eq!(PathState::Exists, PathFacts::from(lazy).state());
+    }
+}
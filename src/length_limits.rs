@@ -0,0 +1,349 @@
+//! Name and path length limits from `pathconf` (unix only)
+//!
+//! A component longer than `NAME_MAX` or a path longer than `PATH_MAX` fails with
+//! `ENAMETOOLONG`, which is easy to miss in generated build paths. Limits are in bytes, not
+//! characters, so a name such as `é` counts twice. Limits depend on the filesystem, so they
+//! are read from the closest existing ancestor of the path.
+use std::{
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+};
+
+/// Smallest `NAME_MAX` and `PATH_MAX` of common filesystems, eCryptfs allows 143 byte names
+/// and macOS 1024 byte paths. Paths within them are not worth a `pathconf` call unless the
+/// error says a name is too long.
+const COMMON_NAME_MAX: u64 = 143;
+const COMMON_PATH_MAX: u64 = 1024;
+
+/// Length limits of the filesystem and the parts of the path that exceed them
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LengthLimits {
+    /// The path `pathconf` was called on, the closest existing ancestor when the path does
+    /// not exist
+    pub(crate) checked: PathBuf,
+    /// Longest component in bytes, `None` when there is no limit
+    pub(crate) name_max: Option<u64>,
+    /// Longest path in bytes, including the nul terminator, `None` when there is no limit
+    pub(crate) path_max: Option<u64>,
+    /// Byte length of the path as it was given
+    pub(crate) path_bytes: usize,
+    /// Components longer than `name_max`
+    pub(crate) long_names: Vec<LongName>,
+    /// The component where the path reaches `path_max`, when it's too long
+    pub(crate) path_max_reached_at: Option<String>,
+}
+
+/// A path component that is longer than `NAME_MAX`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LongName {
+    pub(crate) name: String,
+    pub(crate) bytes: usize,
+    pub(crate) chars: usize,
+}
+
+impl LengthLimits {
+    /// Reads the limits for the closest existing ancestor and checks the path against them
+    #[cfg(unix)]
+    pub(crate) fn new(path: &Path) -> Option<Self> {
+        let checked = crate::abs_path::AbsPath::new(path)
+            .ok()?
+            .nearest_existing()?;
+        let name_max = pathconf(&checked, libc::_PC_NAME_MAX);
+        let path_max = pathconf(&checked, libc::_PC_PATH_MAX);
+        Some(LengthLimits::check(path, checked, name_max, path_max))
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new(_path: &Path) -> Option<Self> {
+        None
+    }
+
+    /// Reads the limits when the path is too long for the smallest common limits, and keeps
+    /// them when the path exceeds the limits of its own filesystem
+    pub(crate) fn when_exceeded(path: &Path) -> Option<Self> {
+        LengthLimits::check(
+            path,
+            PathBuf::new(),
+            Some(COMMON_NAME_MAX),
+            Some(COMMON_PATH_MAX),
+        )
+        .exceeded()
+        .then(|| LengthLimits::new(path))
+        .flatten()
+        .filter(LengthLimits::exceeded)
+    }
+
+    /// True for `ENAMETOOLONG`, the limits are read even when the path looks short enough
+    pub(crate) fn explains(error: &std::io::Error) -> bool {
+        crate::error_facts::name_too_long(error)
+    }
+
+    pub(crate) fn check(
+        path: &Path,
+        checked: PathBuf,
+        name_max: Option<u64>,
+        path_max: Option<u64>,
+    ) -> Self {
+        let long_names = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name),
+                _ => None,
+            })
+            .filter(|name| name_max.is_some_and(|max| name.len() as u64 > max))
+            .map(|name| {
+                let lossy = name.to_string_lossy();
+                LongName {
+                    bytes: name.len(),
+                    chars: lossy.chars().count(),
+                    name: lossy.into_owned(),
+                }
+            })
+            .collect();
+
+        let path_bytes = path.as_os_str().len();
+        // `PATH_MAX` counts the nul terminator, so the longest path is one byte shorter
+        let path_max_reached_at = path_max
+            .filter(|max| path_bytes as u64 >= *max)
+            .and_then(|max| reached_at(path, max));
+
+        LengthLimits {
+            checked,
+            name_max,
+            path_max,
+            path_bytes,
+            long_names,
+            path_max_reached_at,
+        }
+    }
+
+    /// The path the limits were read for, the closest existing ancestor when the path does
+    /// not exist
+    pub fn checked(&self) -> &Path {
+        &self.checked
+    }
+
+    /// Longest component in bytes, `None` when there is no limit
+    pub fn name_max(&self) -> Option<u64> {
+        self.name_max
+    }
+
+    /// Longest path in bytes including the nul terminator, `None` when there is no limit
+    pub fn path_max(&self) -> Option<u64> {
+        self.path_max
+    }
+
+    /// Byte length of the path as it was given
+    pub fn path_bytes(&self) -> usize {
+        self.path_bytes
+    }
+
+    /// Components longer than `NAME_MAX`
+    pub fn long_names(&self) -> &[LongName] {
+        &self.long_names
+    }
+
+    /// True when the path is longer than `PATH_MAX` allows
+    pub fn path_too_long(&self) -> bool {
+        self.path_max
+            .is_some_and(|max| self.path_bytes as u64 >= max)
+    }
+
+    /// True when a component or the whole path is too long
+    pub fn exceeded(&self) -> bool {
+        !self.long_names.is_empty() || self.path_too_long()
+    }
+
+    /// Describes every limit that is exceeded
+    pub(crate) fn violations(&self) -> Vec<String> {
+        let mut violations = self
+            .long_names
+            .iter()
+            .map(|long| {
+                format!(
+                    "{long}, over the `NAME_MAX` of {} bytes",
+                    self.name_max.unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>();
+        if let (true, Some(max)) = (self.path_too_long(), self.path_max) {
+            let reached = self
+                .path_max_reached_at
+                .as_ref()
+                .map(|name| format!(", reached at `{name}`"))
+                .unwrap_or_default();
+            violations.push(format!(
+                "Path is {} bytes, over the `PATH_MAX` of {max} bytes including the nul terminator{reached}",
+                self.path_bytes
+            ));
+        }
+        violations
+    }
+}
+
+impl LongName {
+    /// The component, invalid UTF-8 replaced
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Length in bytes, what the limit counts
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Length in characters
+    pub fn chars(&self) -> usize {
+        self.chars
+    }
+}
+
+impl Display for LongName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Component `{}` is {} bytes", self.name, self.bytes)?;
+        if self.chars != self.bytes {
+            write!(f, " ({} characters)", self.chars)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for LengthLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limit = |max: Option<u64>| match max {
+            Some(max) => format!("{max} bytes"),
+            None => "unlimited".to_string(),
+        };
+        write!(
+            f,
+            "Length limits of `{}`: `NAME_MAX` {}, `PATH_MAX` {}",
+            self.checked.display(),
+            limit(self.name_max),
+            limit(self.path_max)
+        )
+    }
+}
+
+/// Name of the first component where the path is `max` bytes or longer
+fn reached_at(path: &Path, max: u64) -> Option<String> {
+    let mut ancestors = path.ancestors().collect::<Vec<_>>();
+    ancestors.reverse();
+    ancestors
+        .into_iter()
+        .find(|ancestor| ancestor.as_os_str().len() as u64 >= max)
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Returns `None` when there is no limit or it cannot be read
+#[cfg(unix)]
+fn pathconf(path: &Path, name: libc::c_int) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `path` is nul terminated
+    let value = unsafe { libc::pathconf(path.as_ptr(), name) };
+    u64::try_from(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_long_name_in_bytes() {
+        let name = "é".repeat(200);
+        let path = PathBuf::from("/build").join(&name).join("out.o");
+        let limits = LengthLimits::check(&path, PathBuf::from("/build"), Some(255), Some(4096));
+        assert!(!limits.path_too_long());
+        assert_eq!(
+            vec![LongName {
+                name: name.clone(),
+                bytes: 400,
+                chars: 200,
+            }],
+            limits.long_names()
+        );
+        assert_eq!(
+            vec![format!(
+                "Component `{name}` is 400 bytes (200 characters), over the `NAME_MAX` of 255 bytes"
+            )],
+            limits.violations()
+        );
+    }
+
+    #[test]
+    fn test_path_too_long() {
+        let component = "a".repeat(100);
+        let mut path = PathBuf::from("/");
+        for _ in 0..41 {
+            path.push(&component);
+        }
+        assert_eq!(4141, path.as_os_str().len());
+
+        let limits = LengthLimits::check(&path, PathBuf::from("/"), Some(255), Some(4096));
+        assert!(limits.long_names().is_empty());
+        assert!(limits.exceeded());
+        assert_eq!(
+            vec![format!(
+                "Path is 4141 bytes, over the `PATH_MAX` of 4096 bytes including the nul terminator, reached at `{component}`"
+            )],
+            limits.violations()
+        );
+
+        let limits = LengthLimits::check(&path, PathBuf::from("/"), None, None);
+        assert!(!limits.exceeded());
+        assert_eq!(
+            "Length limits of `/`: `NAME_MAX` unlimited, `PATH_MAX` unlimited",
+            limits.to_string()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reads_limits_of_existing_ancestor() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("a".repeat(300)).join("file.txt");
+        let limits = LengthLimits::new(&path).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(tempdir.path()).unwrap(),
+            limits.checked()
+        );
+        let name_max = limits.name_max().unwrap();
+        assert!(name_max < 300);
+        assert_eq!(1, limits.long_names().len());
+
+        let facts = crate::PathFacts::new(&path);
+        assert!(facts.to_string().contains(&format!(
+            " - Component `{}` is 300 bytes, over the `NAME_MAX` of {name_max} bytes\n",
+            "a".repeat(300)
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_short_path_only_reads_limits_for_name_too_long() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        assert_eq!(None, LengthLimits::when_exceeded(&path));
+        assert!(crate::PathFacts::new(&path).length_limits().is_none());
+
+        // Over the smallest common `NAME_MAX`, `pathconf` decides whether it is too long
+        let path = tempdir.path().join("a".repeat(200));
+        let limits = LengthLimits::new(&path).unwrap();
+        assert_eq!(
+            limits.name_max().is_some_and(|max| max < 200),
+            LengthLimits::when_exceeded(&path).is_some()
+        );
+
+        let error = std::io::Error::from_raw_os_error(libc::ENAMETOOLONG);
+        let facts = crate::PathFacts::with_error(&path, &error);
+        let limits = facts.length_limits().unwrap();
+        assert!(!limits.exceeded());
+        assert!(facts.to_string().contains(&limits.to_string()));
+    }
+}
@@ -1,10 +1,52 @@
 #![doc = include_str!("../README.md")]
 mod abs_path;
+mod acl;
+mod ancestor_chain;
 mod canonical_path;
+mod capacity;
+mod error_facts;
 mod fact_check;
+pub mod fs;
+mod fs_error;
 mod happy_path;
+mod inode_flags;
+mod labels;
+mod lazy_path_facts;
+mod length_limits;
+mod mounts;
+mod operation;
+mod ownership;
 mod path_facts;
+mod path_pair_facts;
+mod path_state;
+mod permissions;
+mod probe;
 mod resolved_metadata;
+#[cfg(feature = "serde")]
+mod schema;
 mod style;
+mod symlink_chain;
+mod user_access;
+mod xattr;
 
+pub use acl::{Acl, AclEntry, AclTag};
+pub use ancestor_chain::{Ancestor, AncestorChain, TraversalBreak};
+pub use capacity::Capacity;
+pub use error_facts::Contradiction;
+pub use fs_error::FsError;
+pub use happy_path::DirOk;
+pub use inode_flags::InodeFlags;
+pub use labels::{LabelFacts, PathLabels, ProcessLabel, SelinuxContext};
+pub use lazy_path_facts::LazyPathFacts;
+pub use length_limits::{LengthLimits, LongName};
+pub use mounts::{MountEntry, MountFacts};
+pub use operation::{Blocker, Operation};
+pub use ownership::{Ownership, PermissionClass};
 pub use path_facts::PathFacts;
+pub use path_pair_facts::PathPairFacts;
+pub use path_state::{Existence, PathState};
+pub use permissions::{AccessCheck, Permissions};
+pub use probe::{Probe, ProbeOperation, ProbeReport, ProbeScope};
+pub use resolved_metadata::{Device, ResolvedType};
+pub use symlink_chain::{SymlinkChain, SymlinkEnd, SymlinkHop};
+pub use user_access::{ComponentAccess, UserAccess};
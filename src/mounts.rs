@@ -0,0 +1,468 @@
+//! Mount points and filesystems from `/proc/self/mountinfo` (Linux only)
+//!
+//! A read-only or `noexec` mount fails with `EROFS` or `EACCES` no matter what the mode bits
+//! say, which looks just like a permission problem. Network and FUSE filesystems check
+//! permissions on the server or in a user space daemon, so local checks can disagree with
+//! what actually happens.
+use crate::abs_path::AbsPath;
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Filesystem types where a server decides access
+const NETWORK: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "ceph",
+    "afs",
+    "lustre",
+    "glusterfs",
+];
+
+/// A single line of `/proc/self/mountinfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MountEntry {
+    pub(crate) mount_id: u32,
+    pub(crate) parent_id: u32,
+    pub(crate) major: u32,
+    pub(crate) minor: u32,
+    /// Directory of the filesystem that is mounted, not `/` for bind mounts of a subdirectory
+    pub(crate) root: PathBuf,
+    pub(crate) mount_point: PathBuf,
+    /// Per mount options such as `ro` and `noexec`
+    pub(crate) options: Vec<String>,
+    pub(crate) fs_type: String,
+    pub(crate) source: String,
+    /// Per filesystem options, shared by every mount of the same filesystem
+    pub(crate) super_options: Vec<String>,
+}
+
+/// Mounts that hold a path and its parent
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MountFacts {
+    /// The path the mounts were looked up for, the closest existing ancestor when the path
+    /// does not exist
+    pub(crate) resolved: PathBuf,
+    pub(crate) target: MountEntry,
+    pub(crate) parent: MountEntry,
+    /// Mount points from the root down to `resolved`
+    pub(crate) boundaries: Vec<PathBuf>,
+}
+
+impl MountEntry {
+    /// Parses a line such as `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw`
+    ///
+    /// Paths escape spaces, tabs, newlines, and backslashes as octal such as `\040`.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let (mount, filesystem) = line.split_once(" - ")?;
+        let mut mount = mount.split(' ');
+        let mount_id = mount.next()?.parse().ok()?;
+        let parent_id = mount.next()?.parse().ok()?;
+        let (major, minor) = mount.next()?.split_once(':')?;
+        let root = path_from_bytes(unescape(mount.next()?));
+        let mount_point = path_from_bytes(unescape(mount.next()?));
+        let options = split_options(mount.next()?);
+        // Optional fields such as `shared:1` are not used
+
+        let mut filesystem = filesystem.split(' ');
+        let fs_type = filesystem.next()?.to_string();
+        let source = String::from_utf8_lossy(&unescape(filesystem.next()?)).into_owned();
+        let super_options = filesystem.next().map(split_options).unwrap_or_default();
+
+        Some(MountEntry {
+            mount_id,
+            parent_id,
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+            root,
+            mount_point,
+            options,
+            fs_type,
+            source,
+            super_options,
+        })
+    }
+
+    /// Directory the filesystem is mounted on
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// Filesystem type such as `ext4`, `nfs4`, or `fuse.sshfs`
+    pub fn fs_type(&self) -> &str {
+        &self.fs_type
+    }
+
+    /// What is mounted, such as `/dev/sda1` or `server:/export`
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Per mount options such as `rw`, `nosuid`, and `relatime`
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    /// Directory of the filesystem that is mounted, not `/` for bind mounts of a subdirectory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Writes fail with `EROFS`, either the mount or the whole filesystem is read-only
+    pub fn read_only(&self) -> bool {
+        self.has_option("ro") || self.super_options.iter().any(|option| option == "ro")
+    }
+
+    /// Executing files fails with `EACCES` regardless of execute bits
+    pub fn noexec(&self) -> bool {
+        self.has_option("noexec")
+    }
+
+    /// Setuid and setgid bits are ignored when executing files
+    pub fn nosuid(&self) -> bool {
+        self.has_option("nosuid")
+    }
+
+    /// Device files cannot be opened
+    pub fn nodev(&self) -> bool {
+        self.has_option("nodev")
+    }
+
+    /// A server decides access, such as NFS, SMB, or 9p
+    pub fn is_network(&self) -> bool {
+        let base = self.fs_type.split('.').next().unwrap_or_default();
+        NETWORK.contains(&base)
+    }
+
+    /// A user space daemon decides access, such as sshfs
+    pub fn is_fuse(&self) -> bool {
+        self.fs_type == "fuse"
+            || self.fs_type == "fuseblk"
+            || self.fs_type.starts_with("fuse.")
+            || self.fs_type == "virtiofs"
+    }
+
+    fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option == name)
+    }
+
+    /// Restrictive options that are set, in a fixed order
+    fn restrictions(&self) -> Vec<&'static str> {
+        [
+            (self.read_only(), "ro"),
+            (self.noexec(), "noexec"),
+            (self.nosuid(), "nosuid"),
+            (self.nodev(), "nodev"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+}
+
+impl MountFacts {
+    /// Finds the mounts that hold the path and its parent
+    ///
+    /// Uses the closest existing ancestor for paths that do not exist, that is where they
+    /// would be created. `None` when the mount table cannot be read, such as off Linux.
+    pub(crate) fn new(path: &Path) -> Option<Self> {
+        let absolute = AbsPath::new(path).ok()?;
+        let resolved = absolute.nearest_existing()?;
+        let parent = absolute
+            .parent()
+            .and_then(|parent| parent.nearest_existing())
+            .unwrap_or_else(|| resolved.clone());
+        let table = read_table()?;
+        MountFacts::from_table(&table, resolved, &parent)
+    }
+
+    /// True for errors that a mount can cause, reading the mount table is not worth it
+    /// for others
+    pub(crate) fn explains(kind: ErrorKind) -> bool {
+        matches!(
+            kind,
+            ErrorKind::ReadOnlyFilesystem
+                | ErrorKind::PermissionDenied
+                | ErrorKind::CrossesDevices
+                | ErrorKind::ResourceBusy
+        )
+    }
+
+    pub(crate) fn from_table(
+        table: &[MountEntry],
+        resolved: PathBuf,
+        parent: &Path,
+    ) -> Option<Self> {
+        let target = mount_for(table, &resolved)?.clone();
+        let parent = mount_for(table, parent)?.clone();
+        let mut boundaries = table
+            .iter()
+            .map(|entry| entry.mount_point.clone())
+            .filter(|mount_point| resolved.starts_with(mount_point))
+            .collect::<Vec<_>>();
+        boundaries.sort_by_key(|mount_point| mount_point.components().count());
+        boundaries.dedup();
+
+        Some(MountFacts {
+            resolved,
+            target,
+            parent,
+            boundaries,
+        })
+    }
+
+    /// The path the mounts were looked up for, the closest existing ancestor when the path
+    /// does not exist
+    pub fn resolved(&self) -> &Path {
+        &self.resolved
+    }
+
+    /// Mount that holds the path
+    pub fn target(&self) -> &MountEntry {
+        &self.target
+    }
+
+    /// Mount that holds the parent directory, differs from `target` when the path is a mount
+    /// point
+    pub fn parent(&self) -> &MountEntry {
+        &self.parent
+    }
+
+    /// Mount points crossed from the root down to the path
+    pub fn boundaries(&self) -> &[PathBuf] {
+        &self.boundaries
+    }
+
+    /// True when the path itself is a mount point
+    pub fn is_mount_point(&self) -> bool {
+        self.target.mount_point == self.resolved && self.target != self.parent
+    }
+
+    /// Facts about the mounts that commonly explain a failure
+    pub(crate) fn notes(&self) -> Vec<String> {
+        let mut notes = vec![];
+        let mut entries = vec![&self.target];
+        if self.parent != self.target {
+            entries.push(&self.parent);
+        }
+        for entry in entries {
+            let mount_point = entry.mount_point.display();
+            if entry.read_only() {
+                notes.push(format!(
+                    "`{mount_point}` is mounted read-only, creating, writing, and deleting fail with EROFS regardless of permissions"
+                ));
+            }
+            if entry.is_fuse() {
+                notes.push(format!(
+                    "`{mount_point}` is a FUSE filesystem ({}), its daemon decides access and denies other users, even root, unless mounted with `allow_other`",
+                    entry.fs_type
+                ));
+            } else if entry.is_network() {
+                notes.push(format!(
+                    "`{mount_point}` is a network filesystem ({}), the server decides access and can map uids differently, such as squashing root to `nobody`",
+                    entry.fs_type
+                ));
+            }
+        }
+        notes
+    }
+}
+
+/// The mount that holds a path, the last mounted wins when several share a mount point
+fn mount_for<'a>(table: &'a [MountEntry], path: &Path) -> Option<&'a MountEntry> {
+    table
+        .iter()
+        .filter(|entry| path.starts_with(&entry.mount_point))
+        .max_by_key(|entry| entry.mount_point.components().count())
+}
+
+#[cfg(target_os = "linux")]
+fn read_table() -> Option<Vec<MountEntry>> {
+    let contents = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    Some(contents.lines().filter_map(MountEntry::parse).collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_table() -> Option<Vec<MountEntry>> {
+    None
+}
+
+fn split_options(options: &str) -> Vec<String> {
+    options.split(',').map(str::to_string).collect()
+}
+
+/// Decodes octal escapes such as `\040` for a space
+fn unescape(field: &str) -> Vec<u8> {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match octal {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 4;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+impl Display for MountEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` ({} from `{}`",
+            self.mount_point.display(),
+            self.fs_type,
+            self.source
+        )?;
+        for restriction in self.restrictions() {
+            write!(f, ", {restriction}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    const MOUNTINFO: &str = "\
+28 1 254:0 / / rw,relatime - ext4 /dev/vda rw
+29 28 0:40 / /mnt/my\\040data ro,nosuid,nodev,relatime shared:5 - nfs4 server:/export rw,vers=4.2
+30 28 0:41 / /home/user/remote rw,nosuid,nodev - fuse.sshfs user@host:/srv rw,user_id=1000
+31 28 254:0 /srv /mnt/bind rw,noexec - ext4 /dev/vda rw
+";
+
+    fn table() -> Vec<MountEntry> {
+        MOUNTINFO.lines().filter_map(MountEntry::parse).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let table = table();
+        assert_eq!(4, table.len());
+
+        let nfs = &table[1];
+        assert_eq!(Path::new("/mnt/my data"), nfs.mount_point());
+        assert_eq!("nfs4", nfs.fs_type());
+        assert_eq!("server:/export", nfs.source());
+        assert!(nfs.read_only() && nfs.nosuid() && nfs.nodev() && !nfs.noexec());
+        assert!(nfs.is_network() && !nfs.is_fuse());
+        assert_eq!(
+            "`/mnt/my data` (nfs4 from `server:/export`, ro, nosuid, nodev)",
+            nfs.to_string()
+        );
+
+        assert!(table[2].is_fuse());
+        assert_eq!(Path::new("/srv"), table[3].root());
+        assert!(table[3].noexec());
+        assert_eq!(None, MountEntry::parse("28 1 254:0 / / rw"));
+    }
+
+    #[test]
+    fn test_mount_for_path() {
+        let table = table();
+        let facts = MountFacts::from_table(
+            &table,
+            PathBuf::from("/mnt/my data/dir"),
+            Path::new("/mnt/my data"),
+        )
+        .unwrap();
+        assert_eq!(Path::new("/mnt/my data"), facts.target().mount_point());
+        assert_eq!(facts.target(), facts.parent());
+        assert!(!facts.is_mount_point());
+        assert_eq!(
+            vec![PathBuf::from("/"), PathBuf::from("/mnt/my data")],
+            facts.boundaries()
+        );
+        assert_eq!(
+            vec![
+                "`/mnt/my data` is mounted read-only, creating, writing, and deleting fail with EROFS regardless of permissions",
+                "`/mnt/my data` is a network filesystem (nfs4), the server decides access and can map uids differently, such as squashing root to `nobody`",
+            ],
+            facts.notes()
+        );
+
+        // A mount point is held by its own mount, its parent by the one below
+        let facts =
+            MountFacts::from_table(&table, PathBuf::from("/mnt/bind"), Path::new("/mnt")).unwrap();
+        assert!(facts.is_mount_point());
+        assert_eq!(Path::new("/"), facts.parent().mount_point());
+
+        // Not under `/mnt/my data` even though it shares a prefix
+        let facts =
+            MountFacts::from_table(&table, PathBuf::from("/mnt/my"), Path::new("/mnt")).unwrap();
+        assert_eq!(Path::new("/"), facts.target().mount_point());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_missing_path_uses_existing_ancestor() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("missing").join("file.txt");
+        let facts = MountFacts::new(&path).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(tempdir.path()).unwrap(),
+            facts.resolved()
+        );
+        assert!(facts.resolved().starts_with(facts.target().mount_point()));
+        assert_eq!(
+            Some(Path::new("/")),
+            facts.boundaries().first().map(PathBuf::as_path)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_only_gathered_when_they_explain_a_failure() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        assert!(crate::PathFacts::new(&path).mounts().is_none());
+
+        let not_found = std::io::Error::from(ErrorKind::NotFound);
+        assert!(crate::PathFacts::with_error(&path, &not_found)
+            .mounts()
+            .is_none());
+
+        let read_only = std::io::Error::from(ErrorKind::ReadOnlyFilesystem);
+        assert!(crate::PathFacts::with_error(&path, &read_only)
+            .mounts()
+            .is_some());
+        assert!(
+            crate::PathFacts::for_operation(&path, crate::Operation::CreateFile)
+                .mounts()
+                .is_some()
+        );
+    }
+}
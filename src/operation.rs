@@ -0,0 +1,576 @@
+//! Facts that block a specific operation
+//!
+//! Whether a fact is good or bad depends on what you're trying to do. A path that does not
+//! exist is a problem when reading it, but it's required when creating it with `create_new`.
+//! Knowing the intended operation lets us point at the specific fact that blocks it.
+use crate::{
+    inode_flags::InodeFlags,
+    path_state::{Existence, PathState},
+    permissions::Permissions,
+    resolved_metadata::ResolvedType,
+    PathFacts,
+};
+use std::{fmt::Display, io::ErrorKind, path::Path};
+
+/// A filesystem operation that was attempted on a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Read a file such as `std::fs::read`
+    Read,
+    /// Write a file, creating it if needed, such as `std::fs::write`
+    Write,
+    /// Create or truncate a file such as `std::fs::File::create`
+    CreateFile,
+    /// Create a file that must not already exist such as `OpenOptions::create_new`
+    CreateNew,
+    /// Create a directory such as `std::fs::create_dir`
+    CreateDir,
+    /// Create a directory and all missing parents such as `std::fs::create_dir_all`
+    CreateDirAll,
+    /// Remove a file such as `std::fs::remove_file`
+    RemoveFile,
+    /// Remove an empty directory such as `std::fs::remove_dir`
+    RemoveDir,
+    /// Rename or move the path such as `std::fs::rename`
+    Rename,
+    /// Execute the file
+    Execute,
+    /// List a directory such as `std::fs::read_dir`
+    ReadDir,
+}
+
+impl Operation {
+    /// True when the operation writes data or creates an entry, so it can run out of space
+    pub(crate) fn needs_space(&self) -> bool {
+        matches!(
+            self,
+            Operation::Write
+                | Operation::CreateFile
+                | Operation::CreateNew
+                | Operation::CreateDir
+                | Operation::CreateDirAll
+        )
+    }
+
+    /// Permissions the operation needs on the path itself
+    pub(crate) fn target_permissions(&self) -> Permissions {
+        match self {
+            Operation::Read => Permissions::new(true, false, false),
+            Operation::Write | Operation::CreateFile | Operation::CreateNew => {
+                Permissions::new(false, true, false)
+            }
+            Operation::Execute => Permissions::new(false, false, true),
+            Operation::ReadDir => Permissions::new(true, false, true),
+            Operation::CreateDir
+            | Operation::CreateDirAll
+            | Operation::RemoveFile
+            | Operation::RemoveDir
+            | Operation::Rename => Permissions::new(false, false, false),
+        }
+    }
+
+    /// Permissions the operation needs on the parent directory
+    pub(crate) fn parent_permissions(&self) -> Permissions {
+        match self {
+            Operation::Write
+            | Operation::CreateFile
+            | Operation::CreateNew
+            | Operation::CreateDir
+            | Operation::CreateDirAll
+            | Operation::RemoveFile
+            | Operation::RemoveDir
+            | Operation::Rename => Permissions::new(false, true, true),
+            Operation::Read | Operation::Execute | Operation::ReadDir => {
+                Permissions::new(false, false, true)
+            }
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Read => write!(f, "read"),
+            Operation::Write => write!(f, "write"),
+            Operation::CreateFile => write!(f, "create file"),
+            Operation::CreateNew => write!(f, "create new file"),
+            Operation::CreateDir => write!(f, "create directory"),
+            Operation::CreateDirAll => write!(f, "create directory and parents"),
+            Operation::RemoveFile => write!(f, "remove file"),
+            Operation::RemoveDir => write!(f, "remove directory"),
+            Operation::Rename => write!(f, "rename"),
+            Operation::Execute => write!(f, "execute"),
+            Operation::ReadDir => write!(f, "read directory"),
+        }
+    }
+}
+
+/// A fact that prevents an operation from succeeding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Blocker {
+    DoesNotExist,
+    AlreadyExists,
+    IsADirectory,
+    NotADirectory,
+    DirectoryNotEmpty,
+    MissingRead,
+    MissingWrite,
+    MissingExecute,
+    ParentDoesNotExist,
+    AncestorNotADirectory,
+    ParentInaccessible,
+    ParentMissingWrite,
+    /// Inode flag set with `chattr +i`
+    Immutable,
+    /// Inode flag set with `chattr +a`
+    AppendOnly,
+    ParentImmutable,
+    ParentAppendOnly,
+    /// The path or its parent is on a read-only mount
+    ReadOnlyFilesystem,
+    /// The path is on a `noexec` mount
+    NoexecMount,
+    /// Something is mounted on the path
+    MountPoint,
+}
+
+impl Display for Blocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Blocker::DoesNotExist => write!(f, "path does not exist"),
+            Blocker::AlreadyExists => write!(f, "path already exists"),
+            Blocker::IsADirectory => write!(f, "target is a directory but a file was expected"),
+            Blocker::NotADirectory => {
+                write!(f, "target is not a directory but a directory was expected")
+            }
+            Blocker::DirectoryNotEmpty => write!(f, "directory is not empty"),
+            Blocker::MissingRead => write!(f, "path lacks read permission"),
+            Blocker::MissingWrite => write!(f, "path lacks write permission"),
+            Blocker::MissingExecute => write!(f, "path lacks execute permission"),
+            Blocker::ParentDoesNotExist => write!(f, "parent directory does not exist"),
+            Blocker::AncestorNotADirectory => write!(f, "a prior path is not a directory"),
+            Blocker::ParentInaccessible => write!(f, "parent directory cannot be accessed"),
+            Blocker::ParentMissingWrite => write!(
+                f,
+                "parent lacks write, so files cannot be created, deleted, or renamed"
+            ),
+            Blocker::Immutable => write!(
+                f,
+                "path is immutable, it cannot be modified, renamed, or deleted even by root"
+            ),
+            Blocker::AppendOnly => write!(
+                f,
+                "path is append-only, it can only be opened for appending and cannot be renamed or deleted"
+            ),
+            Blocker::ParentImmutable => write!(
+                f,
+                "parent is immutable, so entries cannot be created, deleted, or renamed even by root"
+            ),
+            Blocker::ParentAppendOnly => write!(
+                f,
+                "parent is append-only, so entries cannot be deleted or renamed"
+            ),
+            Blocker::ReadOnlyFilesystem => write!(
+                f,
+                "filesystem is mounted read-only, so nothing on it can be created, modified, or deleted"
+            ),
+            Blocker::NoexecMount => write!(
+                f,
+                "filesystem is mounted noexec, so files on it cannot be executed"
+            ),
+            Blocker::MountPoint => write!(
+                f,
+                "path is a mount point, it cannot be removed or renamed while mounted"
+            ),
+        }
+    }
+}
+
+/// The operation a `PathFacts` was gathered for and the facts that block it
+#[derive(Debug)]
+pub(crate) struct OperationFacts {
+    pub(crate) operation: Operation,
+    pub(crate) blockers: Vec<Blocker>,
+}
+
+impl OperationFacts {
+    pub(crate) fn new(facts: &PathFacts, operation: Operation) -> Self {
+        OperationFacts {
+            operation,
+            blockers: blockers(facts, operation),
+        }
+    }
+}
+
+fn blockers(facts: &PathFacts, operation: Operation) -> Vec<Blocker> {
+    let mut blockers = vec![];
+    let mounts = facts.mounts();
+    let parent_read_only = mounts.is_some_and(|mounts| mounts.parent().read_only());
+    // An immutable parent or read-only mount also lacks write, name it instead of the permission
+    let parent_write_blocker = if facts.parent_flags().is_some_and(InodeFlags::immutable) {
+        Some(Blocker::ParentImmutable)
+    } else if parent_read_only {
+        Some(Blocker::ReadOnlyFilesystem)
+    } else if facts
+        .parent_permissions()
+        .is_some_and(|permissions| !permissions.write())
+    {
+        Some(Blocker::ParentMissingWrite)
+    } else {
+        None
+    };
+    // Creating entries in an append-only directory works, deleting and renaming them does not
+    let parent_remove_blocker = parent_write_blocker.or_else(|| {
+        facts
+            .parent_flags()
+            .is_some_and(InodeFlags::append_only)
+            .then_some(Blocker::ParentAppendOnly)
+    });
+    let target_flag = facts.flags().and_then(|flags| {
+        if flags.immutable() {
+            Some(Blocker::Immutable)
+        } else if flags.append_only() {
+            Some(Blocker::AppendOnly)
+        } else {
+            None
+        }
+    });
+    let target_write_blocker = target_flag.unwrap_or(
+        if mounts.is_some_and(|mounts| mounts.target().read_only()) {
+            Blocker::ReadOnlyFilesystem
+        } else {
+            Blocker::MissingWrite
+        },
+    );
+    let target_execute_blocker = if mounts.is_some_and(|mounts| mounts.target().noexec()) {
+        Blocker::NoexecMount
+    } else {
+        Blocker::MissingExecute
+    };
+    // Removing or renaming fails with EBUSY, listed before the flags and the parent
+    let mount_point = mounts
+        .is_some_and(|mounts| mounts.is_mount_point())
+        .then_some(Blocker::MountPoint);
+    let lacks = |check: fn(&Permissions) -> bool| {
+        facts
+            .permissions()
+            .is_some_and(|permissions| !check(&permissions))
+    };
+
+    match facts.existence() {
+        Existence::Exists => {
+            let is_dir = facts.resolved_type().map(|kind| kind == ResolvedType::Dir);
+            // Removing and renaming act on a symlink itself, not on what it points to, so the
+            // type, flags, and mount of the target don't apply
+            let is_symlink = facts.symlink_target().is_some();
+            let link_is_dir = if is_symlink { Some(false) } else { is_dir };
+            let (link_mount_point, link_flag) = if is_symlink {
+                (None, None)
+            } else {
+                (mount_point, target_flag)
+            };
+            match operation {
+                Operation::Read | Operation::Write | Operation::CreateFile | Operation::Execute => {
+                    if is_dir == Some(true) {
+                        blockers.push(Blocker::IsADirectory);
+                    }
+                    let (lacking, blocker) = match operation {
+                        Operation::Read => (lacks(Permissions::read), Blocker::MissingRead),
+                        Operation::Execute => (lacks(Permissions::execute), target_execute_blocker),
+                        _ => (lacks(Permissions::write), target_write_blocker),
+                    };
+                    if lacking {
+                        blockers.push(blocker);
+                    }
+                }
+                Operation::CreateNew | Operation::CreateDir => {
+                    blockers.push(Blocker::AlreadyExists);
+                }
+                Operation::CreateDirAll => {
+                    if is_dir == Some(false) {
+                        blockers.push(Blocker::NotADirectory);
+                    }
+                }
+                Operation::ReadDir => {
+                    if is_dir == Some(false) {
+                        blockers.push(Blocker::NotADirectory);
+                    }
+                    if lacks(Permissions::read) {
+                        blockers.push(Blocker::MissingRead);
+                    }
+                }
+                Operation::RemoveFile => {
+                    if link_is_dir == Some(true) {
+                        blockers.push(Blocker::IsADirectory);
+                    }
+                    blockers.extend(link_mount_point);
+                    blockers.extend(link_flag);
+                    blockers.extend(parent_remove_blocker);
+                }
+                Operation::RemoveDir => {
+                    if link_is_dir == Some(false) {
+                        blockers.push(Blocker::NotADirectory);
+                    } else if link_is_dir == Some(true) && has_entries(facts.path()) {
+                        blockers.push(Blocker::DirectoryNotEmpty);
+                    }
+                    blockers.extend(link_mount_point);
+                    blockers.extend(link_flag);
+                    blockers.extend(parent_remove_blocker);
+                }
+                Operation::Rename => {
+                    blockers.extend(link_mount_point);
+                    blockers.extend(link_flag);
+                    blockers.extend(parent_remove_blocker);
+                }
+            }
+        }
+        Existence::Missing => match operation {
+            Operation::Read
+            | Operation::Execute
+            | Operation::ReadDir
+            | Operation::RemoveFile
+            | Operation::RemoveDir
+            | Operation::Rename => blockers.push(Blocker::DoesNotExist),
+            Operation::Write
+            | Operation::CreateFile
+            | Operation::CreateNew
+            | Operation::CreateDir => match facts.state() {
+                PathState::ParentProblem => {
+                    if prior_is_file(facts) {
+                        blockers.push(Blocker::AncestorNotADirectory);
+                    } else {
+                        blockers.push(Blocker::ParentDoesNotExist);
+                    }
+                }
+                _ => blockers.extend(parent_write_blocker),
+            },
+            Operation::CreateDirAll => match facts.prior() {
+                Some(_) if prior_is_file(facts) => blockers.push(Blocker::AncestorNotADirectory),
+                // Parents are created, the first directory that exists must be writable
+                Some(prior)
+                    if prior
+                        .parent_permissions()
+                        .is_some_and(|permissions| !permissions.write()) =>
+                {
+                    blockers.push(if parent_read_only {
+                        Blocker::ReadOnlyFilesystem
+                    } else {
+                        Blocker::ParentMissingWrite
+                    })
+                }
+                Some(_) => {}
+                None => blockers.extend(parent_write_blocker),
+            },
+        },
+        Existence::Unknown(_) => blockers.push(Blocker::ParentInaccessible),
+    }
+
+    blockers
+}
+
+fn prior_is_file(facts: &PathFacts) -> bool {
+    facts.prior().is_some_and(|prior| {
+        prior
+            .resolved_type()
+            .is_some_and(|kind| kind != ResolvedType::Dir)
+    }) || facts
+        .error()
+        .is_some_and(|error| error.kind() == ErrorKind::NotADirectory)
+}
+
+fn has_entries(path: &Path) -> bool {
+    std::fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_remove_dir_not_empty() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("file.txt"), "").unwrap();
+
+        let facts = PathFacts::for_operation(&dir, Operation::RemoveDir);
+        assert_eq!(Some(Operation::RemoveDir), facts.operation());
+        assert_eq!(&[Blocker::DirectoryNotEmpty], facts.blockers());
+        assert!(facts
+            .to_string()
+            .contains(" - Cannot remove directory: directory is not empty\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_symlink_to_directory() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("file.txt"), "").unwrap();
+        let link = tempdir.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+
+        // `unlink` removes the link, `rmdir` fails with ENOTDIR
+        let facts = PathFacts::for_operation(&link, Operation::RemoveFile);
+        assert!(facts.blockers().is_empty());
+        let facts = PathFacts::for_operation(&link, Operation::RemoveDir);
+        assert_eq!(&[Blocker::NotADirectory], facts.blockers());
+        let facts = PathFacts::for_operation(&link, Operation::Rename);
+        assert!(facts.blockers().is_empty());
+
+        // Reading still follows the link
+        let facts = PathFacts::for_operation(&link, Operation::Read);
+        assert_eq!(&[Blocker::IsADirectory], facts.blockers());
+    }
+
+    #[test]
+    fn test_read_directory_and_create_existing() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let facts = PathFacts::for_operation(tempdir.path(), Operation::Read);
+        assert_eq!(&[Blocker::IsADirectory], facts.blockers());
+
+        let facts = PathFacts::for_operation(tempdir.path(), Operation::CreateNew);
+        assert_eq!(&[Blocker::AlreadyExists], facts.blockers());
+    }
+
+    #[test]
+    fn test_missing_paths() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("missing").join("file.txt");
+
+        let facts = PathFacts::for_operation(&path, Operation::Read);
+        assert_eq!(&[Blocker::DoesNotExist], facts.blockers());
+
+        let facts = PathFacts::for_operation(&path, Operation::CreateFile);
+        assert_eq!(&[Blocker::ParentDoesNotExist], facts.blockers());
+
+        let facts = PathFacts::for_operation(&path, Operation::CreateDirAll);
+        assert!(facts.blockers().is_empty());
+        assert!(facts
+            .to_string()
+            .contains(" - No facts found that block create directory and parents\n"));
+
+        std::fs::write(tempdir.path().join("missing"), "").unwrap();
+        let facts = PathFacts::for_operation(&path, Operation::CreateDirAll);
+        assert_eq!(&[Blocker::AncestorNotADirectory], facts.blockers());
+    }
+
+    /// Clears an inode flag on drop so the tempdir can be removed even when an assert fails
+    #[cfg(target_os = "linux")]
+    struct Chattr<'a>(&'a Path, &'static str);
+
+    #[cfg(target_os = "linux")]
+    impl<'a> Chattr<'a> {
+        /// Sets a flag such as `+a`, panics when `chattr` is missing or not permitted
+        fn set(path: &'a Path, flag: &'static str) -> Self {
+            let status = std::process::Command::new("chattr")
+                .arg(flag)
+                .arg(path)
+                .status()
+                .expect("`chattr` is installed");
+            assert!(status.success(), "chattr {flag} needs CAP_LINUX_IMMUTABLE");
+            Chattr(path, flag)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl Drop for Chattr<'_> {
+        fn drop(&mut self) {
+            let _ = std::process::Command::new("chattr")
+                .arg(self.1.replace('+', "-"))
+                .arg(self.0)
+                .status();
+        }
+    }
+
+    /// Sets inode flags on facts gathered from disk, the way `happy_path::state` applies them
+    fn with_flags(mut facts: PathFacts, flags: InodeFlags) -> PathFacts {
+        let happy = facts.state.as_mut().unwrap();
+        happy.permissions = flags.restrict(happy.permissions, happy.resolved_type.is_dir());
+        happy.flags = Some(flags);
+        facts
+    }
+
+    #[test]
+    fn test_append_only_overrides_permissions() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let file = tempdir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        let flags = InodeFlags {
+            append_only: true,
+            ..InodeFlags::default()
+        };
+
+        let facts = with_flags(PathFacts::new(&file), flags);
+        assert_eq!(Some(false), facts.permissions().map(|p| p.write()));
+        assert_eq!(
+            vec![Blocker::AppendOnly],
+            blockers(&facts, Operation::Write)
+        );
+        assert_eq!(
+            vec![Blocker::AppendOnly],
+            blockers(&facts, Operation::RemoveFile)
+        );
+        assert!(blockers(&facts, Operation::Read).is_empty());
+
+        let facts = with_flags(PathFacts::new(tempdir.path()), flags);
+        assert_eq!(Some(true), facts.permissions().map(|p| p.write()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "needs `chattr` and CAP_LINUX_IMMUTABLE, run with `--ignored` as root"]
+    fn test_append_only_flags() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let file = tempdir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        let _flag = Chattr::set(&file, "+a");
+        assert!(std::fs::write(&file, "").is_err());
+
+        let facts = PathFacts::for_operation(&file, Operation::Write);
+        assert!(facts.flags().is_some_and(|flags| flags.append_only()));
+        assert_eq!(Some(false), facts.permissions().map(|p| p.write()));
+        assert_eq!(&[Blocker::AppendOnly], facts.blockers());
+        assert!(facts.to_string().contains(
+            " - Inode flags of `file.txt`: append-only (can only be opened for appending"
+        ));
+
+        let facts = PathFacts::for_operation(&file, Operation::RemoveFile);
+        assert_eq!(&[Blocker::AppendOnly], facts.blockers());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "needs `chattr` and CAP_LINUX_IMMUTABLE, run with `--ignored` as root"]
+    fn test_append_only_parent() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        let _flag = Chattr::set(&dir, "+a");
+
+        let facts = PathFacts::for_operation(&file, Operation::RemoveFile);
+        assert_eq!(Some(true), facts.parent_permissions().map(|p| p.write()));
+        assert_eq!(&[Blocker::ParentAppendOnly], facts.blockers());
+
+        let facts = PathFacts::for_operation(dir.join("new.txt"), Operation::CreateNew);
+        assert!(facts.blockers().is_empty());
+    }
+}
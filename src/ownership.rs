@@ -0,0 +1,475 @@
+//! Unix owner, group, and mode bits of a path
+//!
+//! Effective permissions answer "can I do this?", but not "why not?". When a file was
+//! created by another user, for example `root` in an earlier Docker build step, the owner
+//! and mode bits explain why the current process lacks a permission.
+//!
+//! Names are read from `/etc/passwd` and `/etc/group` directly instead of going through
+//! NSS, so looking them up never reaches out to the network.
+use crate::{
+    acl::{self, Acl},
+    permissions::Permissions,
+    style,
+};
+use std::{fmt::Display, path::Path};
+
+const SETUID: u32 = 0o4000;
+const SETGID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+
+/// Owner, group, and mode bits of a path (unix only)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ownership {
+    pub(crate) uid: u32,
+    pub(crate) gid: u32,
+    pub(crate) user: Option<String>,
+    pub(crate) group: Option<String>,
+    pub(crate) mode: u32,
+    /// Effective uid of the process that gathered the facts
+    ///
+    /// Recorded when gathered, so rendering later (or elsewhere) shows the same facts.
+    pub(crate) process_uid: u32,
+    /// Whether the group is the effective or a supplementary group of the process
+    pub(crate) process_in_group: bool,
+    /// POSIX access ACL when it has entries beyond the mode bits (Linux only)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) acl: Option<Acl>,
+    /// POSIX default ACL that new entries of a directory inherit (Linux only)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) default_acl: Option<Acl>,
+}
+
+/// Which set of mode bits applies to a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PermissionClass {
+    /// Root bypasses read and write bits, searching directories, and executing files when
+    /// any execute bit is set
+    Root,
+    Owner,
+    Group,
+    Other,
+}
+
+impl Display for PermissionClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionClass::Root => write!(f, "root"),
+            PermissionClass::Owner => write!(f, "owner"),
+            PermissionClass::Group => write!(f, "group"),
+            PermissionClass::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// User and groups of the current process
+#[cfg(unix)]
+pub(crate) struct Identity {
+    pub(crate) uid: u32,
+    pub(crate) gids: Vec<u32>,
+}
+
+#[cfg(unix)]
+impl Identity {
+    /// Effective uid, effective gid, and supplementary groups of this process
+    pub(crate) fn current() -> Self {
+        // SAFETY: `geteuid` and `getegid` always succeed and have no side effects
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        let mut gids = vec![gid];
+        gids.extend(supplementary_groups());
+        Identity { uid, gids }
+    }
+}
+
+#[cfg(unix)]
+fn supplementary_groups() -> Vec<u32> {
+    // SAFETY: A size of zero only returns the number of groups and does not write to the pointer
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count <= 0 {
+        return vec![];
+    }
+    let mut groups = vec![0 as libc::gid_t; count as usize];
+    // SAFETY: `groups` has room for `count` entries
+    let written = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+    groups.truncate(written.max(0) as usize);
+    groups
+}
+
+impl Ownership {
+    /// Reads ownership of the path, following symlinks
+    ///
+    /// Returns `None` on platforms without unix ownership or when metadata cannot be read.
+    #[cfg(unix)]
+    pub(crate) fn new(path: &Path) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = std::fs::metadata(path).ok()?;
+        let process = Identity::current();
+        Some(Ownership {
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            user: name_for_id("/etc/passwd", metadata.uid()),
+            group: name_for_id("/etc/group", metadata.gid()),
+            mode: metadata.mode() & 0o7777,
+            process_uid: process.uid,
+            process_in_group: process.gids.contains(&metadata.gid()),
+            acl: Acl::read_with_names(path, acl::ACCESS),
+            default_acl: metadata
+                .is_dir()
+                .then(|| Acl::read_with_names(path, acl::DEFAULT))
+                .flatten(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new(_path: &Path) -> Option<Self> {
+        None
+    }
+
+    /// Numeric user id of the owner
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Numeric group id of the group
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Name of the owner from `/etc/passwd`, if listed
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Name of the group from `/etc/group`, if listed
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// Permission and special bits, such as `0o755`, without the file type
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Mode bits like `ls -l` shows them, such as `rwxr-xr-x`
+    pub fn mode_string(&self) -> String {
+        style::mode_string(self.mode)
+    }
+
+    pub fn setuid(&self) -> bool {
+        self.mode & SETUID != 0
+    }
+
+    pub fn setgid(&self) -> bool {
+        self.mode & SETGID != 0
+    }
+
+    pub fn sticky(&self) -> bool {
+        self.mode & STICKY != 0
+    }
+
+    /// POSIX access ACL, when the path has one (Linux only)
+    pub fn acl(&self) -> Option<&Acl> {
+        self.acl.as_ref()
+    }
+
+    /// POSIX default ACL that new entries inherit, when the directory has one (Linux only)
+    pub fn default_acl(&self) -> Option<&Acl> {
+        self.default_acl.as_ref()
+    }
+
+    /// True when the owner is the effective user of the process that gathered the facts
+    pub fn owned_by_process(&self) -> bool {
+        self.uid == self.process_uid
+    }
+
+    /// Effective uid of the process that gathered the facts
+    pub fn process_uid(&self) -> u32 {
+        self.process_uid
+    }
+
+    /// Which mode bits applied to the process that gathered the facts
+    pub fn class(&self) -> PermissionClass {
+        if self.process_uid == 0 {
+            PermissionClass::Root
+        } else if self.owned_by_process() {
+            PermissionClass::Owner
+        } else if self.process_in_group {
+            PermissionClass::Group
+        } else {
+            PermissionClass::Other
+        }
+    }
+
+    /// Read, write, and execute bits of the class that applies, as `(read, write, execute)`
+    pub(crate) fn class_permissions(&self, is_dir: bool) -> Permissions {
+        let bits = |shift: u32| (self.mode >> shift) & 0o7;
+        let bits = match self.class() {
+            PermissionClass::Root => return Permissions::for_root(self.mode, is_dir),
+            PermissionClass::Owner => bits(6),
+            PermissionClass::Group => bits(3),
+            PermissionClass::Other => bits(0),
+        };
+        Permissions::new(bits & 0o4 != 0, bits & 0o2 != 0, bits & 0o1 != 0)
+    }
+
+    /// Explains which class applies and why that allows or denies the effective permissions
+    ///
+    /// Such as: you are not the owner (uid 1000 ≠ 0); you are not in group `docker` (999);
+    /// 'other' has `r--` so write is denied
+    pub(crate) fn explain(&self, effective: &Permissions, is_dir: bool) -> String {
+        let class = self.class();
+        let mut reasons = vec![];
+        match class {
+            PermissionClass::Root => reasons.push(format!(
+                "you are root (uid {}) so read and write are allowed regardless of mode bits, {}",
+                self.process_uid,
+                if is_dir {
+                    "and directories can always be searched"
+                } else {
+                    "execute needs any execute bit"
+                }
+            )),
+            PermissionClass::Owner => {
+                reasons.push(format!("you are the owner (uid {})", self.uid));
+            }
+            PermissionClass::Group | PermissionClass::Other => {
+                reasons.push(format!(
+                    "you are not the owner (uid {} ≠ {})",
+                    self.process_uid, self.uid
+                ));
+                let group = name_and_id(self.group(), self.gid);
+                if class == PermissionClass::Group {
+                    reasons.push(format!("you are in group {group}"));
+                } else {
+                    reasons.push(format!("you are not in group {group}"));
+                }
+            }
+        }
+
+        let allowed = self.class_permissions(is_dir);
+        if class != PermissionClass::Root {
+            let shift = match class {
+                PermissionClass::Owner => 6,
+                PermissionClass::Group => 3,
+                _ => 0,
+            };
+            let denied = names(&allowed, false);
+            reasons.push(format!(
+                "'{class}' has `{bits}`{denied}",
+                bits = style::mode_string((self.mode >> shift) & 0o7).split_off(6),
+                denied = append_if_any(" so ", &denied, "denied"),
+            ));
+        }
+
+        // Mode bits are not the only thing that decides access
+        let restricted = Permissions::new(
+            allowed.read && !effective.read,
+            allowed.write && !effective.write,
+            allowed.execute && !effective.execute,
+        );
+        let restricted = names(&restricted, true);
+        if !restricted.is_empty() {
+            reasons.push(format!(
+                "mode bits allow {list} but {verb} denied, so something else restricts access such as an ACL, an inode flag, or a read-only filesystem",
+                list = restricted.join(" and "),
+                verb = if restricted.len() == 1 { "it is" } else { "they are" },
+            ));
+        }
+        reasons.join("; ")
+    }
+
+    /// True when the owner is not the effective user of this process or special bits are set
+    ///
+    /// Used to only show ownership when it could explain something.
+    pub(crate) fn is_notable(&self) -> bool {
+        self.setuid() || self.setgid() || self.sticky() || !self.owned_by_process()
+    }
+
+    /// What the special bits mean, for a directory or not
+    pub(crate) fn special_bits(&self, is_dir: bool) -> Vec<&'static str> {
+        let mut bits = vec![];
+        if self.setuid() {
+            bits.push("setuid (runs as the owner)");
+        }
+        if self.setgid() {
+            bits.push(if is_dir {
+                "setgid (new entries inherit the group)"
+            } else {
+                "setgid (runs as the group)"
+            });
+        }
+        if self.sticky() {
+            bits.push(if is_dir {
+                "sticky (only owners can delete or rename entries)"
+            } else {
+                "sticky"
+            });
+        }
+        bits
+    }
+}
+
+/// Names of the permissions that match `allowed`
+fn names(permissions: &Permissions, allowed: bool) -> Vec<&'static str> {
+    [
+        (permissions.read, "read"),
+        (permissions.write, "write"),
+        (permissions.execute, "execute"),
+    ]
+    .into_iter()
+    .filter(|(value, _)| *value == allowed)
+    .map(|(_, name)| name)
+    .collect()
+}
+
+fn append_if_any(prefix: &str, names: &[&str], suffix: &str) -> String {
+    match names.len() {
+        0 => String::new(),
+        1 => format!("{prefix}{} is {suffix}", names[0]),
+        _ => format!("{prefix}{} are {suffix}", names.join(" and ")),
+    }
+}
+
+/// Finds the name for an id in a `name:password:id:...` file such as `/etc/passwd`
+pub(crate) fn name_for_id(file: &str, id: u32) -> Option<String> {
+    std::fs::read_to_string(file)
+        .ok()
+        .and_then(|contents| parse_name_for_id(&contents, id))
+}
+
+fn parse_name_for_id(contents: &str, id: u32) -> Option<String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id_field = fields.nth(1)?;
+            (id_field.parse::<u32>().ok()? == id).then(|| name.to_string())
+        })
+}
+
+fn name_and_id(name: Option<&str>, id: u32) -> String {
+    match name {
+        Some(name) => format!("`{name}` ({id})"),
+        None => format!("{id}"),
+    }
+}
+
+impl Display for Ownership {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "mode {mode:04o} `{string}`, owner {owner}, group {group}",
+            mode = self.mode,
+            string = self.mode_string(),
+            owner = name_and_id(self.user(), self.uid),
+            group = name_and_id(self.group(), self.gid),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_parse_name_for_id() {
+        let passwd = "# comment\nroot:x:0:0:root:/root:/bin/bash\nheroku:x:1000:1000::/home/heroku:/bin/sh\n";
+        assert_eq!(Some("root".to_string()), parse_name_for_id(passwd, 0));
+        assert_eq!(Some("heroku".to_string()), parse_name_for_id(passwd, 1000));
+        assert_eq!(None, parse_name_for_id(passwd, 1001));
+    }
+
+    #[test]
+    fn test_special_bits() {
+        let ownership = Ownership {
+            uid: 0,
+            gid: 0,
+            user: Some("root".to_string()),
+            group: None,
+            mode: 0o3777,
+            process_uid: 0,
+            process_in_group: true,
+            acl: None,
+            default_acl: None,
+        };
+        assert!(!ownership.setuid());
+        assert!(ownership.setgid());
+        assert!(ownership.sticky());
+        assert_eq!(
+            "mode 3777 `rwxrwsrwt`, owner `root` (0), group 0",
+            ownership.to_string()
+        );
+        assert_eq!(
+            vec![
+                "setgid (new entries inherit the group)",
+                "sticky (only owners can delete or rename entries)"
+            ],
+            ownership.special_bits(true)
+        );
+    }
+
+    #[test]
+    fn test_explain_class() {
+        let ownership = Ownership {
+            uid: 0,
+            gid: 999,
+            user: Some("root".to_string()),
+            group: Some("docker".to_string()),
+            mode: 0o664,
+            process_uid: 1000,
+            process_in_group: false,
+            acl: None,
+            default_acl: None,
+        };
+        assert_eq!(PermissionClass::Other, ownership.class());
+        assert_eq!(
+            "you are not the owner (uid 1000 ≠ 0); you are not in group `docker` (999); 'other' has `r--` so write and execute are denied",
+            ownership.explain(&Permissions::new(true, false, false), false)
+        );
+
+        let ownership = Ownership {
+            process_in_group: true,
+            ..ownership
+        };
+        assert_eq!(PermissionClass::Group, ownership.class());
+        assert_eq!(
+            "you are not the owner (uid 1000 ≠ 0); you are in group `docker` (999); 'group' has `rw-` so execute is denied; mode bits allow write but it is denied, so something else restricts access such as an ACL, an inode flag, or a read-only filesystem",
+            ownership.explain(&Permissions::new(true, false, false), false)
+        );
+    }
+
+    #[test]
+    fn test_root_searches_any_directory() {
+        let ownership = Ownership {
+            uid: 1000,
+            gid: 1000,
+            user: None,
+            group: None,
+            mode: 0o600,
+            process_uid: 0,
+            process_in_group: false,
+            acl: None,
+            default_acl: None,
+        };
+        assert_eq!(Permissions::ALL, ownership.class_permissions(true));
+        assert_eq!(
+            Permissions::new(true, true, false),
+            ownership.class_permissions(false)
+        );
+        assert_eq!(
+            "you are root (uid 0) so read and write are allowed regardless of mode bits, and directories can always be searched",
+            ownership.explain(&Permissions::ALL, true)
+        );
+    }
+}
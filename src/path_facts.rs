@@ -1,33 +1,727 @@
 use crate::abs_path::AbsPathError;
-use crate::happy_path::{state, HappyPath, UnhappyPath};
+use crate::capacity::Capacity;
+use crate::error_facts::{Contradiction, ErrorFacts, Focus};
+use crate::happy_path::{state, DirOk, HappyPath, LookedUp, UnhappyPath};
+use crate::inode_flags::InodeFlags;
+use crate::labels::LabelFacts;
+use crate::length_limits::LengthLimits;
+use crate::mounts::MountFacts;
+use crate::operation::{Blocker, Operation, OperationFacts};
+use crate::ownership::Ownership;
+use crate::path_state::{Existence, PathState};
+use crate::permissions::{AccessCheck, Permissions};
+use crate::probe::{ProbeReport, ProbeScope};
 use crate::resolved_metadata::ResolvedType;
 use crate::style::{self, append_if, conditional_perms};
+use crate::symlink_chain::SymlinkChain;
+use crate::user_access::UserAccess;
 use std::{
     fmt::Display,
     path::{Path, PathBuf},
 };
 
 /// Shows helpful facts about a path when `Display`ed.
+#[derive(Debug)]
 pub struct PathFacts {
-    path: PathBuf,
-    state: Result<HappyPath, Box<UnhappyPath>>,
+    pub(crate) path: PathBuf,
+    pub(crate) state: Result<HappyPath, Box<UnhappyPath>>,
+    pub(crate) reported_error: Option<ErrorFacts>,
+    pub(crate) operation: Option<OperationFacts>,
+    pub(crate) as_user: Option<UserAccess>,
+    /// How effective permissions were checked, `None` when deserialized without it
+    pub(crate) access_check: Option<AccessCheck>,
+    pub(crate) probe: Option<ProbeReport>,
+    pub(crate) labels: Option<LabelFacts>,
+    /// Mounts that hold the path and its parent, `None` unless an error or operation needs them
+    pub(crate) mounts: Option<MountFacts>,
+    /// Free space and inodes of the filesystem, `None` unless an error or operation needs them
+    pub(crate) capacity: Option<Capacity>,
+    /// Name and path length limits, `None` unless the path is long or the error needs them
+    pub(crate) length_limits: Option<LengthLimits>,
 }
 
 impl PathFacts {
     pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
         PathFacts {
-            path: path.as_ref().to_owned(),
-            state: state(path.as_ref()),
+            length_limits: LengthLimits::when_exceeded(path),
+            ..PathFacts::from_state(path, state(path))
+        }
+    }
+
+    /// Facts with an already gathered state and nothing else
+    ///
+    /// Optional facts such as mounts and length limits are left for the caller to gather.
+    pub(crate) fn from_state(path: &Path, state: Result<HappyPath, Box<UnhappyPath>>) -> Self {
+        PathFacts {
+            path: path.to_owned(),
+            state,
+            reported_error: None,
+            operation: None,
+            as_user: None,
+            access_check: Some(AccessCheck::current()),
+            probe: None,
+            labels: None,
+            mounts: None,
+            capacity: None,
+            length_limits: None,
+        }
+    }
+
+    /// Gathers facts and leads with the ones that explain the given error
+    ///
+    /// Uses the `ErrorKind` and raw OS error to pick the focus. For example `NotFound`
+    /// shows the first missing component and `PermissionDenied` shows the permissions of
+    /// every ancestor. Mounts, free space, and length limits are only read for errors they can
+    /// cause, such as `EROFS`, `ENOSPC`, and `ENAMETOOLONG`.
+    pub fn with_error(path: impl AsRef<Path>, error: &std::io::Error) -> Self {
+        let mut facts = PathFacts::new(path.as_ref());
+        if MountFacts::explains(error.kind()) {
+            facts.mounts = MountFacts::new(path.as_ref());
+        }
+        if Capacity::explains(error.kind()) {
+            facts.capacity = Capacity::new(path.as_ref());
+        }
+        if LengthLimits::explains(error) {
+            facts.length_limits = LengthLimits::new(path.as_ref());
+        }
+        facts.reported_error = Some(ErrorFacts::new(path.as_ref(), error));
+        facts
+    }
+
+    /// Gathers facts and points out the ones that block the given operation
+    ///
+    /// Permissions the operation does not need are not shown.
+    pub fn for_operation(path: impl AsRef<Path>, operation: Operation) -> Self {
+        let mut facts = PathFacts::new(path.as_ref());
+        facts.mounts = MountFacts::new(path.as_ref());
+        if operation.needs_space() {
+            facts.capacity = Capacity::new(path.as_ref());
+        }
+        facts.operation = Some(OperationFacts::new(&facts, operation));
+        facts
+    }
+
+    /// Also computes permissions of the path and every ancestor for another user
+    ///
+    /// Uses the owner, group, and mode bits, and POSIX ACLs when present, instead of asking
+    /// the kernel about the current process. Pass the primary group first, followed by
+    /// supplementary groups. Useful to validate a layout for a runtime user, such as
+    /// `heroku`, before switching to it.
+    ///
+    /// ```no_run
+    /// use path_facts::PathFacts;
+    ///
+    /// let facts = PathFacts::new("/app/tmp").as_user(1002, &[1002]);
+    /// if let Some(access) = facts.user_access() {
+    ///     assert!(access.permissions().is_some_and(|p| p.write()));
+    /// }
+    /// ```
+    pub fn as_user(mut self, uid: u32, gids: &[u32]) -> Self {
+        self.as_user = Some(UserAccess::new(&self.path, uid, gids));
+        self
+    }
+
+    /// Permissions computed for another user by `as_user`, if any
+    pub fn user_access(&self) -> Option<&UserAccess> {
+        self.as_user.as_ref()
+    }
+
+    /// Also verifies permissions by trying operations on the path and its parent
+    ///
+    /// Permission checks can't see every restriction, such as Landlock, seccomp, AppArmor,
+    /// or a read-only bind mount. Probes open the path (`O_PATH` and `O_RDONLY`) and list
+    /// directories. `ProbeScope::CreateInParent` also creates and immediately deletes a
+    /// uniquely named file in the parent. Results that disagree with the permissions are
+    /// called out, for example "permissions say writable, but create failed with EROFS".
+    pub fn probe(mut self, scope: ProbeScope) -> Self {
+        self.probe = Some(ProbeReport::new(&self, scope));
+        self
+    }
+
+    /// Results of `probe`, if it was used
+    pub fn probe_report(&self) -> Option<&ProbeReport> {
+        self.probe.as_ref()
+    }
+
+    /// Also gathers extended attributes and SELinux or AppArmor labels
+    ///
+    /// Lists extended attribute names and sizes of the path and its parent, decodes their
+    /// `security.selinux` contexts, and reads the label of this process from `/proc/self/attr`
+    /// as the active security module wrote it. Label combinations that commonly cause denials
+    /// are called out, such as a file labeled `user_home_t` read by a confined service.
+    pub fn labels(mut self) -> Self {
+        self.labels = Some(LabelFacts::new(&self));
+        self
+    }
+
+    /// Labels gathered by `labels`, if it was used
+    pub fn label_facts(&self) -> Option<&LabelFacts> {
+        self.labels.as_ref()
+    }
+
+    /// Mounts that hold the path and its parent, such as a read-only or network filesystem
+    ///
+    /// Only gathered by `with_error` for errors a mount can cause, and by `for_operation`.
+    /// `None` otherwise, or when the mount table could not be read, such as off Linux.
+    pub fn mounts(&self) -> Option<&MountFacts> {
+        self.mounts.as_ref()
+    }
+
+    /// Free space and inodes of the filesystem that holds the path, or that it would be
+    /// created on
+    ///
+    /// Only gathered by `with_error` for `ENOSPC` and `EDQUOT`, and by `for_operation` for
+    /// operations that write or create. `None` otherwise, or when `statvfs` failed, such as
+    /// off unix.
+    pub fn capacity(&self) -> Option<&Capacity> {
+        self.capacity.as_ref()
+    }
+
+    /// `NAME_MAX` and `PATH_MAX` of the filesystem and the parts of the path that exceed them
+    ///
+    /// Only read when the path has a component over 143 bytes or is 1024 bytes or longer,
+    /// the smallest limits of common filesystems such as eCryptfs and macOS, and kept when the
+    /// path exceeds the limits of its own filesystem. Always read by `with_error` for
+    /// `ENAMETOOLONG`, which also covers filesystems with even smaller limits. `None`
+    /// otherwise, or when the limits could not be read, such as off unix.
+    pub fn length_limits(&self) -> Option<&LengthLimits> {
+        self.length_limits.as_ref()
+    }
+
+    /// How the effective permissions in these facts were checked
+    ///
+    /// `None` for facts deserialized from a document that does not include it.
+    pub fn access_check(&self) -> Option<AccessCheck> {
+        self.access_check
+    }
+
+    /// The operation facts were gathered for, if any
+    pub fn operation(&self) -> Option<Operation> {
+        self.operation.as_ref().map(|facts| facts.operation)
+    }
+
+    /// Facts that block the operation, empty when no operation was given
+    pub fn blockers(&self) -> &[Blocker] {
+        self.operation
+            .as_ref()
+            .map(|facts| facts.blockers.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Facts that disagree with the error passed to `with_error`
+    ///
+    /// Usually means the disk changed between the failure and gathering facts.
+    pub fn contradictions(&self) -> Vec<Contradiction> {
+        self.reported_error
+            .as_ref()
+            .map(|error| error.contradictions(self))
+            .unwrap_or_default()
+    }
+
+    /// Facts about the closest ancestor without a parent problem, when the parent has a problem
+    pub(crate) fn prior(&self) -> Option<&PathFacts> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Err(UnhappyPath::ParentProblem { prior, .. }) => Some(prior),
+            _ => None,
+        }
+    }
+
+    /// The path as it was given
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Which facts could be gathered about the path
+    pub fn state(&self) -> PathState {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Ok(_) => PathState::Exists,
+            Err(UnhappyPath::AbsPathError(AbsPathError::PathIsEmpty(_))) => PathState::EmptyPath,
+            Err(UnhappyPath::AbsPathError(AbsPathError::CannotReadCWD(_, _))) => {
+                PathState::CannotReadCwd
+            }
+            Err(UnhappyPath::IsRoot(_)) => PathState::IsRoot,
+            Err(UnhappyPath::ParentProblem { .. }) => PathState::ParentProblem,
+            Err(UnhappyPath::DoesNotExist { .. }) => PathState::DoesNotExist,
+            Err(UnhappyPath::ParentNotListable { .. }) => PathState::ParentNotListable,
+            Err(UnhappyPath::CannotCanonicalize { .. }) => PathState::CannotCanonicalize,
+            Err(UnhappyPath::CannotMetadata { .. }) => PathState::CannotMetadata,
+            Err(UnhappyPath::CannotReadLink { .. }) => PathState::CannotReadLink,
+        }
+    }
+
+    /// Whether the path exists, is missing, or we're not allowed to find out
+    pub fn existence(&self) -> Existence {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Ok(_) | Err(UnhappyPath::IsRoot(_)) => Existence::Exists,
+            Err(UnhappyPath::AbsPathError(AbsPathError::PathIsEmpty(_)))
+            | Err(UnhappyPath::DoesNotExist { .. }) => Existence::Missing,
+            Err(UnhappyPath::AbsPathError(AbsPathError::CannotReadCWD(_, error))) => {
+                Existence::Unknown(error.kind())
+            }
+            // If a parent directory of a path does not exist, the path does not exist
+            Err(UnhappyPath::ParentProblem { error, .. }) => match error.kind() {
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::NotADirectory => {
+                    Existence::Missing
+                }
+                kind => Existence::Unknown(kind),
+            },
+            Err(UnhappyPath::ParentNotListable { target, .. }) => {
+                if target.is_some() {
+                    Existence::Exists
+                } else {
+                    Existence::Missing
+                }
+            }
+            Err(UnhappyPath::CannotCanonicalize {
+                absolute, parent, ..
+            })
+            | Err(UnhappyPath::CannotMetadata {
+                absolute, parent, ..
+            })
+            | Err(UnhappyPath::CannotReadLink {
+                absolute, parent, ..
+            }) => {
+                if parent.has_entry(absolute) {
+                    Existence::Exists
+                } else {
+                    Existence::Missing
+                }
+            }
+        }
+    }
+
+    /// The error that prevented gathering more facts, if any
+    pub fn error(&self) -> Option<&std::io::Error> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Ok(_)
+            | Err(UnhappyPath::IsRoot(_))
+            | Err(UnhappyPath::DoesNotExist { .. })
+            | Err(UnhappyPath::AbsPathError(AbsPathError::PathIsEmpty(_))) => None,
+            Err(UnhappyPath::AbsPathError(AbsPathError::CannotReadCWD(_, error)))
+            | Err(UnhappyPath::ParentProblem { error, .. })
+            | Err(UnhappyPath::ParentNotListable { error, .. })
+            | Err(UnhappyPath::CannotCanonicalize { error, .. })
+            | Err(UnhappyPath::CannotMetadata { error, .. })
+            | Err(UnhappyPath::CannotReadLink { error, .. }) => Some(error),
+        }
+    }
+
+    /// Absolute representation of the path
+    pub fn absolute(&self) -> Option<&Path> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Ok(happy) => Some(happy.absolute.as_ref()),
+            Err(UnhappyPath::AbsPathError(_)) => None,
+            Err(UnhappyPath::IsRoot(absolute))
+            | Err(UnhappyPath::ParentProblem { absolute, .. })
+            | Err(UnhappyPath::DoesNotExist { absolute, .. })
+            | Err(UnhappyPath::ParentNotListable { absolute, .. })
+            | Err(UnhappyPath::CannotCanonicalize { absolute, .. })
+            | Err(UnhappyPath::CannotMetadata { absolute, .. })
+            | Err(UnhappyPath::CannotReadLink { absolute, .. }) => Some(absolute.as_ref()),
+        }
+    }
+
+    /// Canonical representation of the path with all symlinks resolved
+    pub fn canonical(&self) -> Option<&Path> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Ok(happy) => Some(happy.canonical.as_ref()),
+            Err(UnhappyPath::CannotMetadata { canonical, .. })
+            | Err(UnhappyPath::CannotReadLink { canonical, .. }) => Some(canonical.as_ref()),
+            Err(_) => self.looked_up().map(|target| target.canonical.as_ref()),
+        }
+    }
+
+    /// Target of the symlink when the path is a symlink
+    pub fn symlink_target(&self) -> Option<&Path> {
+        match &self.state {
+            Ok(happy) => happy.symlink_target.as_ref(),
+            Err(_) => self
+                .looked_up()
+                .and_then(|target| target.symlink_target.as_ref()),
+        }
+        .map(AsRef::as_ref)
+    }
+
+    /// Links followed from the path, when it is a symlink that cannot be resolved
+    pub fn symlink_chain(&self) -> Option<&SymlinkChain> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Err(UnhappyPath::CannotCanonicalize { symlink_chain, .. }) => symlink_chain.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Type of the path after following symlinks
+    pub fn resolved_type(&self) -> Option<ResolvedType> {
+        match &self.state {
+            Ok(happy) => Some(happy.resolved_type.clone()),
+            Err(_) => self.looked_up().map(|target| target.resolved_type.clone()),
+        }
+    }
+
+    /// Effective permissions of the path
+    pub fn permissions(&self) -> Option<Permissions> {
+        match &self.state {
+            Ok(happy) => Some(happy.permissions),
+            Err(_) => self.looked_up().map(|target| target.permissions),
+        }
+    }
+
+    /// Owner, group, and mode bits of the path (unix only)
+    pub fn ownership(&self) -> Option<&Ownership> {
+        match &self.state {
+            Ok(happy) => happy.ownership.as_ref(),
+            Err(_) => self
+                .looked_up()
+                .and_then(|target| target.ownership.as_ref()),
+        }
+    }
+
+    /// Inode flags of the path such as immutable or append-only (Linux only)
+    ///
+    /// `None` when no flags are set or they could not be read.
+    pub fn flags(&self) -> Option<&InodeFlags> {
+        match &self.state {
+            Ok(happy) => happy.flags.as_ref(),
+            Err(_) => self.looked_up().and_then(|target| target.flags.as_ref()),
+        }
+    }
+
+    /// Inode flags of the parent directory, even when it could not be listed (Linux only)
+    pub fn parent_flags(&self) -> Option<&InodeFlags> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Err(UnhappyPath::ParentNotListable { parent, .. }) => parent.flags.as_ref(),
+            _ => self.parent().and_then(DirOk::flags),
+        }
+    }
+
+    /// Effective permissions of the parent directory, even when it could not be listed
+    pub fn parent_permissions(&self) -> Option<Permissions> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Err(UnhappyPath::ParentNotListable { parent, .. }) => Some(parent.permissions),
+            _ => self.parent().map(DirOk::permissions),
+        }
+    }
+
+    /// Facts about a path looked up directly because its parent could not be listed
+    fn looked_up(&self) -> Option<&LookedUp> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Err(UnhappyPath::ParentNotListable { target, .. }) => target.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Parent directory, when it exists and could be listed
+    ///
+    /// See `parent_permissions` for a parent that can be searched but not listed.
+    pub fn parent(&self) -> Option<&DirOk> {
+        match self.state.as_ref().map_err(|e| &**e) {
+            Ok(happy) => Some(&happy.parent),
+            Err(UnhappyPath::DoesNotExist { parent, .. })
+            | Err(UnhappyPath::CannotCanonicalize { parent, .. })
+            | Err(UnhappyPath::CannotMetadata { parent, .. })
+            | Err(UnhappyPath::CannotReadLink { parent, .. }) => Some(parent),
+            Err(_) => None,
         }
     }
 }
 
 impl Display for PathFacts {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.state.as_ref().map_err(|e| &**e) {
+        let rendered = StateDisplay(self).to_string();
+        // Focused facts go right after the first line so they stand out
+        let (first, rest) = rendered.split_once('\n').unwrap_or((&rendered, ""));
+        writeln!(f, "{first}")?;
+        if let Some(error) = &self.reported_error {
+            for contradiction in error.contradictions(self) {
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("Contradiction: {contradiction}"))
+                )?;
+            }
+            write!(f, "{error}")?;
+        }
+        if let Some(OperationFacts {
+            operation,
+            blockers,
+        }) = &self.operation
+        {
+            if blockers.is_empty() {
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("No facts found that block {operation}"))
+                )?;
+            }
+            for blocker in blockers {
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("Cannot {operation}: {blocker}"))
+                )?;
+            }
+        }
+        if self.access_check == Some(AccessCheck::Computed) {
+            writeln!(
+                f,
+                "{}",
+                style::bullet(format!(
+                    "Permissions were computed from the {} for the effective uid and groups, `faccessat` does not support `AT_EACCESS` here",
+                    AccessCheck::Computed
+                ))
+            )?;
+        }
+        if let Some(mounts) = &self.mounts {
+            fmt_mounts(f, self, mounts)?;
+        }
+        if let Some(capacity) = &self.capacity {
+            fmt_capacity(f, self, capacity)?;
+        }
+        if let Some(limits) = &self.length_limits {
+            let violations = limits.violations();
+            let name_too_long = self
+                .reported_error
+                .as_ref()
+                .is_some_and(|error| matches!(error.focus, Some(Focus::NameTooLong { .. })));
+            if name_too_long && violations.is_empty() {
+                writeln!(f, "{}", style::bullet(limits.to_string()))?;
+            }
+            for violation in violations {
+                writeln!(f, "{}", style::bullet(violation))?;
+            }
+        }
+        // Opt-in sections go at the end, attached to the facts without a blank line
+        let sections = [
+            self.as_user.as_ref().map(ToString::to_string),
+            self.probe.as_ref().map(ToString::to_string),
+            self.labels.as_ref().map(ToString::to_string),
+        ];
+        if sections.iter().all(Option::is_none) {
+            return write!(f, "{rest}");
+        }
+        let rest = rest.trim_end();
+        if !rest.is_empty() {
+            writeln!(f, "{rest}")?;
+        }
+        for section in sections.iter().flatten() {
+            writeln!(f, "{}", style::bullet(section.trim_end()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Shows ownership when it could explain a problem, such as a different owner or special bits
+///
+/// When a relevant permission is denied, also explains which permission class applied.
+fn fmt_ownership(
+    f: &mut std::fmt::Formatter<'_>,
+    name: impl Display,
+    ownership: Option<&Ownership>,
+    is_dir: bool,
+    permissions: &Permissions,
+    relevant: &Permissions,
+) -> std::fmt::Result {
+    let Some(ownership) = ownership else {
+        return Ok(());
+    };
+    if ownership.is_notable() {
+        writeln!(
+            f,
+            "{}",
+            style::bullet(format!(
+                "Ownership of {name}: {ownership}{special}",
+                special = append_if(", ", ownership.special_bits(is_dir).join(", "))
+            ))
+        )?;
+    }
+    if let Some(acl) = ownership.acl() {
+        writeln!(f, "{}", style::bullet(format!("ACL of {name}: {acl}")))?;
+        for note in acl.mask_notes() {
+            writeln!(f, "{}", style::bullet(format!("ACL of {name}: {note}")))?;
+        }
+    }
+    if let Some(acl) = ownership.default_acl() {
+        writeln!(
+            f,
+            "{}",
+            style::bullet(format!(
+                "Default ACL of {name} (inherited by new entries): {acl}"
+            ))
+        )?;
+    }
+    // Most files aren't meant to be executed, only call it out when an operation needs it
+    let execute_relevant = relevant.execute && (is_dir || relevant != &Permissions::ALL);
+    let denied = (relevant.read && !permissions.read)
+        || (relevant.write && !permissions.write)
+        || (execute_relevant && !permissions.execute);
+    if denied {
+        writeln!(
+            f,
+            "{}",
+            style::bullet(format!(
+                "Permissions of {name}: {}",
+                ownership.explain(permissions, is_dir)
+            ))
+        )?;
+    }
+    Ok(())
+}
+
+/// Shows the mounts that hold the path when they could explain a problem
+///
+/// Read-only, network, and FUSE mounts are always shown. `noexec`, `nosuid`, and `nodev` are
+/// common on `/tmp`, so they are only shown when the path is affected by them.
+fn fmt_mounts(
+    f: &mut std::fmt::Formatter<'_>,
+    facts: &PathFacts,
+    mounts: &MountFacts,
+) -> std::fmt::Result {
+    let name = format!("`{}`", style::filename_or_path(&facts.path));
+    let target = mounts.target();
+    let mount_point = target.mount_point().display();
+    let resolved_type = facts.resolved_type();
+    let ownership = facts.ownership();
+    let is_file = resolved_type == Some(ResolvedType::File);
+
+    let mut notes = mounts.notes();
+    let executable = match facts.operation() {
+        Some(operation) => operation == Operation::Execute,
+        None => is_file && ownership.is_some_and(|ownership| ownership.mode() & 0o111 != 0),
+    };
+    if target.noexec() && executable {
+        notes.push(format!(
+            "`{mount_point}` is mounted noexec, executing files fails with EACCES regardless of execute bits"
+        ));
+    }
+    if target.nosuid() && is_file && ownership.is_some_and(|o| o.setuid() || o.setgid()) {
+        notes.push(format!(
+            "`{mount_point}` is mounted nosuid, setuid and setgid bits are ignored when executing"
+        ));
+    }
+    if target.nodev()
+        && matches!(
+            resolved_type,
+            Some(ResolvedType::BlockDevice(_) | ResolvedType::CharDevice(_))
+        )
+    {
+        notes.push(format!(
+            "`{mount_point}` is mounted nodev, device files on it cannot be opened"
+        ));
+    }
+    if mounts.is_mount_point() {
+        notes.push(format!(
+            "{name} is a mount point, removing or renaming it fails with EBUSY"
+        ));
+    }
+    if notes.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(f, "{}", style::bullet(format!("Mount of {name}: {target}")))?;
+    if mounts.parent() != target {
+        writeln!(
+            f,
+            "{}",
+            style::bullet(format!("Mount of parent: {}", mounts.parent()))
+        )?;
+    }
+    if mounts.boundaries().len() > 1 {
+        let boundaries = mounts
+            .boundaries()
+            .iter()
+            .map(|boundary| format!("`{}`", boundary.display()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            f,
+            "{}",
+            style::bullet(format!("Mount points from the root down: {boundaries}"))
+        )?;
+    }
+    for note in notes {
+        writeln!(f, "{}", style::bullet(note))?;
+    }
+    Ok(())
+}
+
+/// Shows free space and inodes when the filesystem is nearly full or the error was about space
+fn fmt_capacity(
+    f: &mut std::fmt::Formatter<'_>,
+    facts: &PathFacts,
+    capacity: &Capacity,
+) -> std::fmt::Result {
+    let reported = facts.reported_error.as_ref().map(|error| error.kind);
+    let warnings = capacity.warnings();
+    let about_space = matches!(
+        reported,
+        Some(std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded)
+    );
+    if warnings.is_empty() && !about_space {
+        return Ok(());
+    }
+
+    writeln!(
+        f,
+        "{}",
+        style::bullet(format!(
+            "Filesystem of `{}`: {capacity}",
+            capacity.path().display()
+        ))
+    )?;
+    for warning in warnings {
+        writeln!(f, "{}", style::bullet(warning))?;
+    }
+    if reported == Some(std::io::ErrorKind::QuotaExceeded) {
+        writeln!(
+            f,
+            "{}",
+            style::bullet("Disk quotas limit a user or group, not the filesystem, `quota` shows how much of it is used")
+        )?;
+    }
+    Ok(())
+}
+
+/// Shows inode flags such as immutable, they restrict a path beyond its permissions
+fn fmt_flags(
+    f: &mut std::fmt::Formatter<'_>,
+    name: impl Display,
+    flags: Option<&InodeFlags>,
+    is_dir: bool,
+) -> std::fmt::Result {
+    let Some(flags) = flags else {
+        return Ok(());
+    };
+    writeln!(
+        f,
+        "{}",
+        style::bullet(format!(
+            "Inode flags of {name}: {}",
+            flags.descriptions(is_dir).join("; ")
+        ))
+    )
+}
+
+/// Renders the gathered state without any operation or error specific facts
+struct StateDisplay<'a>(&'a PathFacts);
+
+impl Display for StateDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let StateDisplay(facts) = self;
+        let (target_relevant, parent_relevant) = facts
+            .operation
+            .as_ref()
+            .map(|facts| {
+                (
+                    facts.operation.target_permissions(),
+                    facts.operation.parent_permissions(),
+                )
+            })
+            .unwrap_or((Permissions::ALL, Permissions::ALL));
+        match facts.state.as_ref().map_err(|e| &**e) {
             Ok(happy) => {
-                writeln!(f, "exists `{}`", self.path.display())?;
-                if self.path.is_relative() {
+                writeln!(f, "exists `{}`", facts.path.display())?;
+                if facts.path.is_relative() {
                     writeln!(
                         f,
                         "{}",
@@ -50,14 +744,14 @@ impl Display for PathFacts {
                 writeln!(
                     f,
                     "{}",
-                    style::bullet(style::fmt_dir(&happy.parent, |entry| {
+                    style::bullet(style::fmt_dir(&happy.parent, &parent_relevant, |entry| {
                         if entry == &happy.absolute {
                             Some(format!(
                                 "({file_type}{permissions})",
                                 file_type = happy.resolved_type,
                                 permissions = append_if(
                                     ": ",
-                                    conditional_perms(happy.read, happy.write, happy.execute)
+                                    conditional_perms(&happy.permissions, &target_relevant),
                                 )
                             ))
                         } else {
@@ -65,6 +759,34 @@ impl Display for PathFacts {
                         }
                     }))
                 )?;
+                fmt_ownership(
+                    f,
+                    format!("`{}`", style::filename_or_path(&facts.path)),
+                    happy.ownership.as_ref(),
+                    happy.resolved_type.is_dir(),
+                    &happy.permissions,
+                    &target_relevant,
+                )?;
+                fmt_flags(
+                    f,
+                    format!("`{}`", style::filename_or_path(&facts.path)),
+                    happy.flags.as_ref(),
+                    happy.resolved_type.is_dir(),
+                )?;
+                fmt_ownership(
+                    f,
+                    format!("parent {}", happy.parent.absolute),
+                    happy.parent.ownership.as_ref(),
+                    true,
+                    &happy.parent.permissions,
+                    &parent_relevant,
+                )?;
+                fmt_flags(
+                    f,
+                    format!("parent {}", happy.parent.absolute),
+                    happy.parent.flags.as_ref(),
+                    true,
+                )?;
             }
             Err(UnhappyPath::AbsPathError(AbsPathError::PathIsEmpty(path))) => {
                 writeln!(f, "path `{}` is empty", path.display())?;
@@ -83,54 +805,33 @@ impl Display for PathFacts {
             Err(UnhappyPath::ParentProblem {
                 absolute,
                 parent,
-                error: _,
+                error,
+                ancestors,
+                prior,
             }) => {
-                writeln!(f, "cannot access `{}`", self.path.display())?;
-                if self.path.is_relative() {
+                writeln!(f, "cannot access `{}`", facts.path.display())?;
+                if facts.path.is_relative() {
                     writeln!(f, "{}", style::bullet(format!("Absolute: {absolute}",)))?;
                 }
-
-                let mut prior_dir = parent.clone();
-                let mut prior_state = state(parent.as_ref());
-                while let Err(UnhappyPath::ParentProblem {
-                    absolute: _,
-                    parent,
-                    error: _,
-                }) = prior_state.as_ref().map_err(|e| &**e)
+                for (ancestor, error) in std::iter::once((parent, error))
+                    .chain(ancestors.iter().map(|(ancestor, error)| (ancestor, error)))
                 {
-                    prior_dir = parent.clone();
-                    prior_state = state(prior_dir.as_ref());
+                    writeln!(f, "{}", style::bullet(format!("{ancestor}: {error}")))?;
                 }
-                match &prior_state {
-                    Ok(HappyPath {
-                        resolved_type: ResolvedType::File,
-                        ..
-                    }) => {
+
+                match &prior.state {
+                    Ok(HappyPath { resolved_type, .. }) if !resolved_type.is_dir() => {
                         writeln!(f, "{}", style::bullet("Prior path is not a directory"))?;
-                        writeln!(
-                            f,
-                            "{}",
-                            style::bullet(format!(
-                                "Prior path {}",
-                                PathFacts::new(prior_dir.as_ref())
-                            ))
-                        )?
+                        writeln!(f, "{}", style::bullet(format!("Prior path {prior}")))?
                     }
                     _ => {
-                        writeln!(
-                            f,
-                            "{}",
-                            style::bullet(format!(
-                                "Prior directory {}",
-                                PathFacts::new(prior_dir.as_ref())
-                            ))
-                        )?;
+                        writeln!(f, "{}", style::bullet(format!("Prior directory {prior}")))?;
                     }
                 }
             }
             Err(UnhappyPath::DoesNotExist { absolute, parent }) => {
-                writeln!(f, "does not exist `{}`", self.path.display())?;
-                if self.path.is_relative() {
+                writeln!(f, "does not exist `{}`", facts.path.display())?;
+                if facts.path.is_relative() {
                     writeln!(f, "{}", style::bullet(format!("Absolute: {absolute}",)))?;
                 }
 
@@ -139,29 +840,131 @@ impl Display for PathFacts {
                     "{}",
                     style::bullet(format!(
                         "Missing `{filename}` from parent directory:\n{dir}",
-                        filename = style::filename_or_path(&self.path),
-                        dir = style::fmt_dir(parent, |_| { None },)
+                        filename = style::filename_or_path(&facts.path),
+                        dir = style::fmt_dir(parent, &parent_relevant, |_| { None },)
                     ))
                 )?;
-                if !parent.write {
+                if !parent.permissions.write {
                     writeln!(
                         f,
                         "{}",
                         style::bullet("Parent directory is missing write permissions (cannot create, delete, or modify files)")
                     )?;
                 }
+                fmt_ownership(
+                    f,
+                    format!("parent {}", parent.absolute),
+                    parent.ownership.as_ref(),
+                    true,
+                    &parent.permissions,
+                    &parent_relevant,
+                )?;
+                fmt_flags(
+                    f,
+                    format!("parent {}", parent.absolute),
+                    parent.flags.as_ref(),
+                    true,
+                )?;
+            }
+            Err(UnhappyPath::ParentNotListable {
+                absolute,
+                parent,
+                error,
+                target,
+            }) => {
+                if target.is_some() {
+                    writeln!(f, "exists `{}`", facts.path.display())?;
+                } else {
+                    writeln!(f, "does not exist `{}`", facts.path.display())?;
+                }
+                if facts.path.is_relative() {
+                    writeln!(f, "{}", style::bullet(format!("Absolute: {absolute}",)))?;
+                }
+                if let Some(LookedUp {
+                    canonical,
+                    symlink_target: Some(symlink_target),
+                    ..
+                }) = target
+                {
+                    writeln!(f, "{}", style::bullet(format!("Canonical: {canonical}")))?;
+                    writeln!(
+                        f,
+                        "{}",
+                        style::bullet(format!("Symlink target: {symlink_target}"))
+                    )?;
+                }
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!(
+                        "Cannot list other entries of parent directory {dir}: {error}",
+                        dir = parent.absolute
+                    ))
+                )?;
+                let dir = format!(
+                    "{path}{permissions}",
+                    path = parent.absolute,
+                    permissions = append_if(
+                        " ",
+                        conditional_perms(&parent.permissions, &parent_relevant)
+                    )
+                );
+                match target {
+                    Some(target) => writeln!(
+                        f,
+                        "{}",
+                        style::bullet(format!(
+                            "Found `{filename}` by looking it up directly:\n{dir}\n{entry}",
+                            filename = style::filename_or_path(&facts.path),
+                            entry = style::fmt_dir_entries_annotate(
+                                std::slice::from_ref(absolute),
+                                |_| Some(format!(
+                                    "({file_type}{permissions})",
+                                    file_type = target.resolved_type,
+                                    permissions = append_if(
+                                        ": ",
+                                        conditional_perms(&target.permissions, &target_relevant),
+                                    )
+                                ))
+                            )
+                        ))
+                    )?,
+                    None => writeln!(
+                        f,
+                        "{}",
+                        style::bullet(format!(
+                            "Missing `{filename}` when looking it up directly in:\n{dir}",
+                            filename = style::filename_or_path(&facts.path),
+                        ))
+                    )?,
+                }
+                if let Some(target) = target {
+                    fmt_flags(
+                        f,
+                        format!("`{}`", style::filename_or_path(&facts.path)),
+                        target.flags.as_ref(),
+                        target.resolved_type.is_dir(),
+                    )?;
+                }
+                fmt_flags(
+                    f,
+                    format!("parent {}", parent.absolute),
+                    parent.flags.as_ref(),
+                    true,
+                )?;
             }
             Err(UnhappyPath::CannotCanonicalize {
                 absolute,
                 parent,
                 error,
+                symlink_chain,
             }) => {
                 if parent.has_entry(absolute) {
-                    writeln!(f, "exists `{}`", self.path.display())?;
+                    writeln!(f, "exists `{}`", facts.path.display())?;
                 } else {
-                    writeln!(f, "does not exist `{}`", self.path.display())?;
+                    writeln!(f, "does not exist `{}`", facts.path.display())?;
                 }
-                if self.path.is_relative() {
+                if facts.path.is_relative() {
                     writeln!(f, "{}", style::bullet(format!("Absolute: {absolute}",)))?;
                 }
                 writeln!(
@@ -169,10 +972,17 @@ impl Display for PathFacts {
                     "{}",
                     style::bullet(format!("Cannot canonicalize due to error `{error}`",))
                 )?;
+                if let Some(chain) = symlink_chain {
+                    writeln!(
+                        f,
+                        "{}",
+                        style::bullet(format!("Symlink chain:\n{}", chain.fmt_hops()).trim_end())
+                    )?;
+                }
                 writeln!(
                     f,
                     "{}",
-                    style::bullet(style::fmt_dir(parent, |entry| {
+                    style::bullet(style::fmt_dir(parent, &parent_relevant, |entry| {
                         if entry == absolute {
                             Some("(exists)".to_string())
                         } else {
@@ -188,11 +998,11 @@ impl Display for PathFacts {
                 error,
             }) => {
                 if parent.has_entry(absolute) {
-                    writeln!(f, "exists `{}`", self.path.display())?;
+                    writeln!(f, "exists `{}`", facts.path.display())?;
                 } else {
-                    writeln!(f, "does not exist `{}`", self.path.display())?;
+                    writeln!(f, "does not exist `{}`", facts.path.display())?;
                 }
-                if self.path.is_relative() {
+                if facts.path.is_relative() {
                     writeln!(f, "{}", style::bullet(format!("Absolute: {absolute}",)))?;
                 }
                 writeln!(f, "{}", style::bullet(format!("Canonical: {canonical}",)))?;
@@ -204,7 +1014,7 @@ impl Display for PathFacts {
                 writeln!(
                     f,
                     "{}",
-                    style::bullet(style::fmt_dir(parent, |entry| {
+                    style::bullet(style::fmt_dir(parent, &parent_relevant, |entry| {
                         if entry == absolute {
                             Some("(exists)".to_string())
                         } else {
@@ -220,11 +1030,11 @@ impl Display for PathFacts {
                 error,
             }) => {
                 if parent.has_entry(absolute) {
-                    writeln!(f, "exists `{}`", self.path.display())?;
+                    writeln!(f, "exists `{}`", facts.path.display())?;
                 } else {
-                    writeln!(f, "does not exist `{}`", self.path.display())?;
+                    writeln!(f, "does not exist `{}`", facts.path.display())?;
                 }
-                if self.path.is_relative() {
+                if facts.path.is_relative() {
                     writeln!(f, "{}", style::bullet(format!("Absolute: {absolute}",)))?;
                 }
                 writeln!(f, "{}", style::bullet(format!("Canonical: {canonical}",)))?;
@@ -236,7 +1046,7 @@ impl Display for PathFacts {
                 writeln!(
                     f,
                     "{}",
-                    style::bullet(style::fmt_dir(parent, |entry| {
+                    style::bullet(style::fmt_dir(parent, &parent_relevant, |entry| {
                         if entry == absolute {
                             Some("(exists)".to_string())
                         } else {
@@ -272,6 +1082,9 @@ mod tests {
         std::fs::write(tempdir.path().join("a"), "").unwrap();
         let expected = formatdoc! {"
             cannot access `/path/to/directory/a/b/c/does_not_exist.txt`
+             - `/path/to/directory/a/b/c`: Not a directory (os error 20)
+             - `/path/to/directory/a/b`: Not a directory (os error 20)
+             - `/path/to/directory/a`: Not a directory (os error 20)
              - Prior path is not a directory
              - Prior path exists `/path/to/directory/a`
                 - `/path/to/directory`
@@ -299,6 +1112,9 @@ mod tests {
             .join("does_not_exist.txt");
         let expected = formatdoc! {"
             cannot access `/path/to/directory/a/b/c/does_not_exist.txt`
+             - `/path/to/directory/a/b/c`: No such file or directory (os error 2)
+             - `/path/to/directory/a/b`: No such file or directory (os error 2)
+             - `/path/to/directory/a`: No such file or directory (os error 2)
              - Prior directory does not exist `/path/to/directory/a`
                 - Missing `a` from parent directory:
                   `/path/to/directory`
@@ -362,4 +1178,306 @@ mod tests {
         let facts = PathFacts::new(path);
         assert_eq!(expected.trim(), format!("{facts}").trim());
     }
+
+    #[test]
+    fn test_structured_file_exists() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("exists.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let facts = PathFacts::new(&path);
+        assert_eq!(PathState::Exists, facts.state());
+        assert_eq!(Existence::Exists, facts.existence());
+        assert_eq!(Some(ResolvedType::File), facts.resolved_type());
+        assert_eq!(Some(path.as_path()), facts.absolute());
+        assert!(facts.permissions().unwrap().read());
+        assert!(!facts.permissions().unwrap().execute());
+
+        let parent = facts.parent().unwrap();
+        assert_eq!(tempdir.path(), parent.path());
+        assert_eq!(vec![path.as_path()], parent.entries().collect::<Vec<_>>());
+        assert!(facts.error().is_none());
+    }
+
+    #[test]
+    fn test_structured_missing() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let facts = PathFacts::new(tempdir.path().join("does_not_exist.txt"));
+        assert_eq!(PathState::DoesNotExist, facts.state());
+        assert_eq!(Existence::Missing, facts.existence());
+        assert!(facts.permissions().is_none());
+        assert!(facts.parent().is_some());
+
+        let facts = PathFacts::new(tempdir.path().join("a").join("b.txt"));
+        assert_eq!(PathState::ParentProblem, facts.state());
+        assert_eq!(Existence::Missing, facts.existence());
+        assert_eq!(
+            std::io::ErrorKind::NotFound,
+            facts.error().map(std::io::Error::kind).unwrap()
+        );
+        assert!(facts.parent().is_none());
+
+        let facts = PathFacts::new("");
+        assert_eq!(PathState::EmptyPath, facts.state());
+        assert_eq!(Existence::Missing, facts.existence());
+    }
+
+    #[test]
+    fn test_parent_not_listable() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "").unwrap();
+
+        // Facts as they would be gathered when `dir` has execute but not read
+        let absolute = crate::abs_path::AbsPath::new(&path).unwrap();
+        let facts = PathFacts {
+            access_check: None,
+            ..PathFacts::from_state(
+                &path,
+                Err(Box::new(UnhappyPath::ParentNotListable {
+                    absolute: absolute.clone(),
+                    parent: crate::happy_path::SearchOnlyDir {
+                        absolute: crate::abs_path::AbsPath::new(&dir).unwrap(),
+                        permissions: Permissions::new(false, true, true),
+                        flags: None,
+                    },
+                    error: std::io::Error::from_raw_os_error(13),
+                    target: Some(LookedUp {
+                        canonical: crate::canonical_path::CanonicalPath::new(&absolute).unwrap(),
+                        symlink_target: None,
+                        resolved_type: ResolvedType::File,
+                        permissions: Permissions::new(true, true, false),
+                        ownership: None,
+                        flags: None,
+                    }),
+                })),
+            )
+        };
+        assert_eq!(PathState::ParentNotListable, facts.state());
+        assert_eq!(Existence::Exists, facts.existence());
+        assert_eq!(Some(ResolvedType::File), facts.resolved_type());
+        assert!(facts.parent().is_none());
+        assert_eq!(
+            Some(Permissions::new(false, true, true)),
+            facts.parent_permissions()
+        );
+        let expected = formatdoc! {"
+            exists `/path/to/directory/dir/file.txt`
+             - Cannot list other entries of parent directory `/path/to/directory/dir`: Permission denied (os error 13)
+             - Found `file.txt` by looking it up directly:
+               `/path/to/directory/dir` ❌ read, ✅ write, ✅ execute
+                 └── `file.txt` (file: ✅ read, ✅ write, ❌ execute)
+        "}
+        .replace(
+            "/path/to/directory",
+            format!("{}", tempdir.path().display()).as_str(),
+        );
+        assert_eq!(expected.trim(), format!("{facts}").trim());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_search_only_parent() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("file.txt"), "").unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o300)).unwrap();
+
+        let exists = PathFacts::new(dir.join("file.txt"));
+        let missing = PathFacts::new(dir.join("missing.txt"));
+        let listable = std::fs::read_dir(&dir).is_ok();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        // Privileged users (root) can list the directory anyway
+        if listable {
+            return;
+        }
+
+        assert_eq!(PathState::ParentNotListable, exists.state());
+        assert_eq!(Existence::Exists, exists.existence());
+        assert_eq!(Some(ResolvedType::File), exists.resolved_type());
+        assert!(exists.permissions().is_some());
+
+        assert_eq!(PathState::ParentNotListable, missing.state());
+        assert_eq!(Existence::Missing, missing.existence());
+        assert!(missing
+            .to_string()
+            .contains("Missing `missing.txt` when looking it up directly in:"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(tempdir.path().join("file.txt"), "").unwrap();
+
+        // Relative targets are relative to the directory of the link
+        std::os::unix::fs::symlink("../file.txt", dir.join("link")).unwrap();
+        let facts = PathFacts::new(dir.join("link"));
+        assert_eq!(
+            Some(dir.join("../file.txt").as_path()),
+            facts.symlink_target()
+        );
+
+        std::os::unix::fs::symlink("missing.txt", dir.join("broken")).unwrap();
+        let facts = PathFacts::new(dir.join("broken"));
+        assert_eq!(PathState::CannotCanonicalize, facts.state());
+        assert_eq!(
+            Some(crate::SymlinkEnd::Broken),
+            facts.symlink_chain().map(SymlinkChain::end)
+        );
+        assert!(facts.to_string().contains(&format!(
+            " - Symlink chain:\n    - `{}` -> `missing.txt` (`{}`) <- chain breaks here: target does not exist\n",
+            dir.join("broken").display(),
+            dir.join("missing.txt").display()
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ownership() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        std::fs::set_permissions(tempdir.path(), std::fs::Permissions::from_mode(0o1777)).unwrap();
+
+        let facts = PathFacts::new(&path);
+        let ownership = facts.ownership().unwrap();
+        assert_eq!(0o640, ownership.mode());
+        assert_eq!("rw-r-----", ownership.mode_string());
+        assert!(ownership.owned_by_process());
+        assert!(facts.parent().unwrap().ownership().unwrap().sticky());
+
+        let rendered = facts.to_string();
+        assert!(!rendered.contains("Ownership of `file.txt`"));
+        assert!(rendered.contains(&format!(
+            " - Ownership of parent `{}`: mode 1777 `rwxrwxrwt`",
+            tempdir.path().display()
+        )));
+        assert!(rendered.contains(", sticky (only owners can delete or rename entries)\n"));
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o444)).unwrap();
+        let facts = PathFacts::new(&path);
+        let class = facts.ownership().unwrap().class();
+        let rendered = facts.to_string();
+        if class == crate::PermissionClass::Root {
+            assert!(!rendered.contains("Permissions of `file.txt`"));
+        } else {
+            assert!(rendered.contains(&format!(
+                " - Permissions of `file.txt`: you are the owner (uid {uid}); 'owner' has `r--` so write and execute are denied\n",
+                uid = facts.ownership().unwrap().uid()
+            )));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_as_user() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::fs::set_permissions(tempdir.path(), std::fs::Permissions::from_mode(0o700)).unwrap();
+        let other = std::fs::metadata(&path).unwrap().uid() + 1002;
+
+        let facts = PathFacts::new(&path);
+        assert!(facts.user_access().is_none());
+
+        let facts = PathFacts::new(&path).as_user(other, &[other]);
+        let access = facts.user_access().unwrap();
+        assert_eq!(other, access.uid());
+        assert_eq!(tempdir.path(), access.blocked_by().unwrap().path());
+        let rendered = facts.to_string();
+        assert!(rendered.contains(&format!(
+            " - Permissions for uid {other} (groups: {other}) from the root down:\n    - `/` ("
+        )));
+        assert!(rendered.ends_with(&format!(
+            "    - `{}` (✅ read, ❌ write, ❌ execute)\n",
+            path.display()
+        )));
+    }
+
+    #[test]
+    fn test_access_check() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let mut facts = PathFacts::new(&path);
+        assert_eq!(Some(AccessCheck::current()), facts.access_check());
+        if cfg!(target_os = "linux") {
+            assert_eq!(Some(AccessCheck::EffectiveAccess), facts.access_check());
+        }
+        assert!(!facts.to_string().contains("Permissions were computed"));
+
+        facts.access_check = Some(AccessCheck::Computed);
+        assert!(facts.to_string().contains(
+            " - Permissions were computed from the owner, group, mode bits, and ACLs for the effective uid and groups, `faccessat` does not support `AT_EACCESS` here\n"
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_acl() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        // user::rw- user:4242:rwx group::r-- mask::r-- other::---
+        let access = crate::acl::tests::encode(&[
+            (0x01, 0o6, u32::MAX),
+            (0x02, 0o7, 4242),
+            (0x04, 0o4, u32::MAX),
+            (0x10, 0o4, u32::MAX),
+            (0x20, 0o0, u32::MAX),
+        ]);
+        // user::rwx group::r-x other::---
+        let default = crate::acl::tests::encode(&[
+            (0x01, 0o7, u32::MAX),
+            (0x04, 0o5, u32::MAX),
+            (0x20, 0o0, u32::MAX),
+        ]);
+        if !crate::xattr::set(&path, crate::acl::ACCESS, &access)
+            || !crate::xattr::set(tempdir.path(), crate::acl::DEFAULT, &default)
+        {
+            // Filesystem without ACL support
+            return;
+        }
+        let name = crate::ownership::name_for_id("/etc/passwd", 4242)
+            .unwrap_or_else(|| "4242".to_string());
+
+        let facts = PathFacts::new(&path);
+        let acl = facts.ownership().unwrap().acl().unwrap();
+        assert_eq!(5, acl.entries().len());
+        assert_eq!(
+            Some(Permissions::new(true, false, false)),
+            acl.mask_permissions()
+        );
+        let parent = facts.parent().unwrap().ownership().unwrap();
+        assert!(parent.acl().is_none());
+        assert!(parent.default_acl().is_some());
+
+        let rendered = facts.to_string();
+        assert!(rendered.contains(&format!(
+            " - ACL of `file.txt`: `user::rw-`, `user:{name}:rwx`, `group::r--`, `mask::r--`, `other::---`\n"
+        )));
+        assert!(rendered.contains(&format!(
+            " - ACL of `file.txt`: mask `r--` removes write and execute from `user:{name}:rwx`\n"
+        )));
+        assert!(rendered.contains(&format!(
+            " - Default ACL of parent `{}` (inherited by new entries): `user::rwx`, `group::r-x`, `other::---`\n",
+            tempdir.path().display()
+        )));
+    }
 }
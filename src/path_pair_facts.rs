@@ -0,0 +1,201 @@
+//! Facts about two paths involved in the same operation
+//!
+//! Operations like rename, copy, hard_link, and symlink can fail because of how the two
+//! paths relate to each other, even when each path looks fine on its own.
+use crate::{
+    abs_path::{AbsPath, Nearest},
+    style, PathFacts,
+};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// Shows facts about a `from` and `to` path, and how they relate, when `Display`ed.
+#[derive(Debug)]
+pub struct PathPairFacts {
+    from: PathFacts,
+    to: PathFacts,
+    from_resolved: Option<PathBuf>,
+    to_resolved: Option<PathBuf>,
+    same_device: Option<bool>,
+}
+
+impl PathPairFacts {
+    pub fn new(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Self {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let from_nearest = AbsPath::new(from).ok().and_then(|abs| abs.nearest());
+        let to_nearest = AbsPath::new(to).ok().and_then(|abs| abs.nearest());
+        let same_device = match (&from_nearest, &to_nearest) {
+            (Some(from), Some(to)) => device(from).zip(device(to)).map(|(a, b)| a == b),
+            _ => None,
+        };
+
+        PathPairFacts {
+            from: PathFacts::new(from),
+            to: PathFacts::new(to),
+            from_resolved: from_nearest.map(|nearest| nearest.resolved),
+            to_resolved: to_nearest.map(|nearest| nearest.resolved),
+            same_device,
+        }
+    }
+
+    pub fn from(&self) -> &PathFacts {
+        &self.from
+    }
+
+    pub fn to(&self) -> &PathFacts {
+        &self.to
+    }
+
+    /// Whether both paths (or their closest existing ancestors) are on the same device
+    ///
+    /// Renaming or hard linking across devices fails with `EXDEV`. Returns `None` when
+    /// it cannot be determined, such as on platforms without device ids.
+    pub fn same_device(&self) -> Option<bool> {
+        self.same_device
+    }
+
+    /// True when both paths exist and `to` is a different type than `from`
+    pub fn to_type_differs(&self) -> bool {
+        match (self.from.resolved_type(), self.to.resolved_type()) {
+            (Some(from), Some(to)) => from != to,
+            _ => false,
+        }
+    }
+
+    /// True when `to` is nested inside of `from`, after resolving symlinks of existing ancestors
+    pub fn to_inside_from(&self) -> bool {
+        match (&self.from_resolved, &self.to_resolved) {
+            (Some(from), Some(to)) => to != from && to.starts_with(from),
+            _ => false,
+        }
+    }
+
+    /// True when both paths exist and resolve to the same canonical path
+    pub fn same_canonical(&self) -> bool {
+        match (self.from.canonical(), self.to.canonical()) {
+            (Some(from), Some(to)) => from == to,
+            _ => false,
+        }
+    }
+}
+
+impl Display for PathPairFacts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "From path {}", self.from.to_string().trim_end())?;
+        writeln!(f, "To path {}", self.to.to_string().trim_end())?;
+
+        let mut facts = vec![];
+        match self.same_device {
+            Some(true) => facts.push("Both paths are on the same device".to_string()),
+            Some(false) => facts.push(
+                "Paths are on different devices (rename and hard link cannot cross devices)"
+                    .to_string(),
+            ),
+            None => {}
+        }
+        if self.to_type_differs() {
+            if let (Some(from), Some(to)) = (self.from.resolved_type(), self.to.resolved_type()) {
+                facts.push(format!("To path is a {to} but from path is a {from}"));
+            }
+        }
+        if self.to_inside_from() {
+            facts.push("To path is inside of from path".to_string());
+        }
+        if let (true, Some(canonical)) = (self.same_canonical(), self.to.canonical()) {
+            facts.push(format!(
+                "Both paths resolve to the same canonical path `{}`",
+                canonical.display()
+            ));
+        }
+
+        if !facts.is_empty() {
+            writeln!(f, "Comparing paths:")?;
+            for fact in facts {
+                writeln!(f, "{}", style::bullet(fact))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn device(nearest: &Nearest) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(&nearest.canonical)
+        .ok()
+        .map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_nearest: &Nearest) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::formatdoc;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_rename_into_missing_dir() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let from = tempdir.path().join("from.txt");
+        let to = tempdir.path().join("missing").join("to.txt");
+        std::fs::write(&from, "").unwrap();
+
+        let facts = PathPairFacts::new(&from, &to);
+        assert_eq!(Some(true), facts.same_device());
+        assert!(!facts.to_inside_from());
+        assert!(!facts.same_canonical());
+
+        let expected = formatdoc! {"
+            From path exists `/path/to/directory/from.txt`
+             - `/path/to/directory`
+                 └── `from.txt` (file: ✅ read, ✅ write, ❌ execute)
+            To path cannot access `/path/to/directory/missing/to.txt`
+             - `/path/to/directory/missing`: No such file or directory (os error 2)
+             - Prior directory does not exist `/path/to/directory/missing`
+                - Missing `missing` from parent directory:
+                  `/path/to/directory`
+                    └── `from.txt`
+            Comparing paths:
+             - Both paths are on the same device
+        "}
+        .replace(
+            "/path/to/directory",
+            format!("{}", tempdir.path().display()).as_str(),
+        );
+        assert_eq!(expected.trim(), facts.to_string().trim());
+    }
+
+    #[test]
+    fn test_to_inside_from_and_type_differs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let from = tempdir.path().join("dir");
+        std::fs::create_dir(&from).unwrap();
+        std::fs::write(from.join("file.txt"), "").unwrap();
+
+        let facts = PathPairFacts::new(&from, from.join("file.txt"));
+        assert!(facts.to_inside_from());
+        assert!(facts.to_type_differs());
+        let rendered = facts.to_string();
+        assert!(rendered.contains("To path is a file but from path is a directory"));
+        assert!(rendered.contains("To path is inside of from path"));
+    }
+
+    #[test]
+    fn test_same_canonical() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let from = tempdir.path().join("file.txt");
+        std::fs::write(&from, "").unwrap();
+
+        let facts = PathPairFacts::new(&from, tempdir.path().join(".").join("file.txt"));
+        assert!(facts.same_canonical());
+        assert!(!facts.to_inside_from());
+    }
+}
@@ -0,0 +1,54 @@
+//! Public, structured view of the facts gathered about a path
+//!
+//! The `Display` output of `PathFacts` is meant for humans. These types
+//! are meant for code that wants to branch on the facts.
+
+/// Which facts could be gathered about a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathState {
+    /// Path exists and all facts could be gathered
+    Exists,
+    /// Path is the root path and has no parent directory
+    IsRoot,
+    /// Parent directory can be listed and does not contain the path
+    DoesNotExist,
+    /// Parent directory could not be canonicalized or listed
+    ParentProblem,
+    /// Parent directory can be searched but not listed, the path was looked up directly
+    ///
+    /// Check `existence` to see if the lookup found the path.
+    ParentNotListable,
+    /// Path exists, but it (or a symlink in it) cannot be resolved
+    CannotCanonicalize,
+    /// Path exists, but its metadata cannot be read
+    CannotMetadata,
+    /// Path exists and is a symlink, but the link cannot be read
+    CannotReadLink,
+    /// An empty path was given
+    EmptyPath,
+    /// Relative path could not be made absolute because the current working directory cannot be read
+    CannotReadCwd,
+}
+
+/// Whether or not a path exists on disk
+///
+/// Failing to access a path does not guarantee it doesn't exist. It might mean you don't
+/// have permission even to know whether it exists. When that happens the existence is
+/// `Unknown` and carries the kind of error that prevented finding out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Existence {
+    /// Path has an entry on disk
+    ///
+    /// A dangling symlink counts as existing, the link is there even though its target is
+    /// not. Check `resolved_type` to see whether the path resolves.
+    Exists,
+    /// Path has no entry, because its parent does not list it or an ancestor is missing or
+    /// not a directory
+    Missing,
+    /// Looking up the path failed with an error that does not say whether it exists, such as
+    /// a parent that cannot be searched
+    ///
+    /// Carries the `ErrorKind` that stopped the lookup.
+    Unknown(std::io::ErrorKind),
+}
@@ -0,0 +1,211 @@
+//! Effective permissions of a path for the current process
+//!
+//! Effective permissions include values inherited from parent directories, they
+//! answer "can I do this?" rather than "what are the mode bits?".
+//!
+//! Plain `access(2)` checks the *real* uid and gid, which gives wrong answers for setuid
+//! programs and processes that changed their effective uid. On unix we ask the kernel with
+//! `faccessat(AT_EACCESS)`, which checks the effective ids. Where that flag isn't available
+//! permissions are computed from the owner, group, mode bits, and ACLs for the effective uid
+//! and groups instead. Which method was used is recorded as an `AccessCheck`.
+use std::path::Path;
+
+/// Effective read, write, and execute permissions of a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Permissions {
+    pub(crate) read: bool,
+    pub(crate) write: bool,
+    pub(crate) execute: bool,
+}
+
+impl Permissions {
+    /// Every permission, used to show all permissions when filtering by relevance
+    pub(crate) const ALL: Permissions = Permissions::new(true, true, true);
+
+    pub(crate) const fn new(read: bool, write: bool, execute: bool) -> Self {
+        Permissions {
+            read,
+            write,
+            execute,
+        }
+    }
+
+    /// What root can do regardless of the owner and group
+    ///
+    /// Root can read and write anything and search any directory, but only execute files
+    /// that have at least one execute bit.
+    pub(crate) const fn for_root(mode: u32, is_dir: bool) -> Self {
+        Permissions::new(true, true, is_dir || mode & 0o111 != 0)
+    }
+
+    /// Checks effective permissions of the path on disk, using `AccessCheck::current`
+    #[cfg(unix)]
+    pub(crate) fn effective(path: &Path) -> Self {
+        match AccessCheck::current() {
+            AccessCheck::EffectiveAccess => Permissions {
+                read: eaccess(path, libc::R_OK).is_ok(),
+                write: eaccess(path, libc::W_OK).is_ok(),
+                execute: eaccess(path, libc::X_OK).is_ok(),
+            },
+            _ => computed(path),
+        }
+    }
+
+    /// Checks effective permissions of the path on disk, using `AccessCheck::current`
+    #[cfg(not(unix))]
+    pub(crate) fn effective(path: &Path) -> Self {
+        use faccess::{AccessMode, PathExt};
+
+        Permissions {
+            read: path.access(AccessMode::READ).is_ok(),
+            write: path.access(AccessMode::WRITE).is_ok(),
+            execute: path.access(AccessMode::EXECUTE).is_ok(),
+        }
+    }
+
+    pub fn read(&self) -> bool {
+        self.read
+    }
+
+    pub fn write(&self) -> bool {
+        self.write
+    }
+
+    pub fn execute(&self) -> bool {
+        self.execute
+    }
+}
+
+/// How effective permissions were checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum AccessCheck {
+    /// The kernel checked the effective uid and gid with `faccessat(AT_EACCESS)`
+    EffectiveAccess,
+    /// Computed from the owner, group, mode bits, and ACLs for the effective uid and groups
+    ///
+    /// Used when `AT_EACCESS` is not available. Does not know about read-only mounts,
+    /// immutable files, or security modules such as SELinux.
+    Computed,
+    /// The platform's own check through the `faccess` crate, such as `AccessCheck` on Windows
+    Platform,
+}
+
+impl AccessCheck {
+    /// The method `Permissions` are checked with in this process
+    ///
+    /// Probes whether `faccessat` accepts `AT_EACCESS` once and remembers the answer.
+    pub fn current() -> Self {
+        #[cfg(unix)]
+        {
+            static CURRENT: std::sync::OnceLock<AccessCheck> = std::sync::OnceLock::new();
+            *CURRENT.get_or_init(|| match eaccess(Path::new("/"), libc::F_OK) {
+                Err(error)
+                    if matches!(
+                        error.raw_os_error(),
+                        Some(libc::EINVAL | libc::ENOSYS | libc::ENOTSUP)
+                    ) =>
+                {
+                    AccessCheck::Computed
+                }
+                _ if AT_EACCESS == 0 => AccessCheck::Computed,
+                _ => AccessCheck::EffectiveAccess,
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            AccessCheck::Platform
+        }
+    }
+}
+
+impl std::fmt::Display for AccessCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessCheck::EffectiveAccess => write!(f, "`faccessat` with `AT_EACCESS`"),
+            AccessCheck::Computed => write!(f, "owner, group, mode bits, and ACLs"),
+            AccessCheck::Platform => write!(f, "the platform's access check"),
+        }
+    }
+}
+
+// Android's libc does not provide `AT_EACCESS`, zero falls back to a computed check
+#[cfg(all(unix, not(target_os = "android")))]
+const AT_EACCESS: libc::c_int = libc::AT_EACCESS;
+#[cfg(target_os = "android")]
+const AT_EACCESS: libc::c_int = 0;
+
+/// `faccessat` against the effective uid and gid
+#[cfg(unix)]
+fn eaccess(path: &Path, mode: libc::c_int) -> Result<(), std::io::Error> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `path` is nul terminated and outlives the call
+    if unsafe { libc::faccessat(libc::AT_FDCWD, path.as_ptr(), mode, AT_EACCESS) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Computes permissions for the effective uid and groups of this process
+///
+/// Like `faccessat`, denies everything when an ancestor cannot be searched.
+#[cfg(unix)]
+fn computed(path: &Path) -> Permissions {
+    let identity = crate::ownership::Identity::current();
+    crate::user_access::UserAccess::new(path, identity.uid, &identity.gids)
+        .permissions()
+        .unwrap_or(Permissions::new(false, false, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[cfg(unix)]
+    #[test]
+    fn test_computed_matches_effective_access() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        for mode in [0o000, 0o400, 0o644, 0o755] {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            let effective = Permissions {
+                read: eaccess(&path, libc::R_OK).is_ok(),
+                write: eaccess(&path, libc::W_OK).is_ok(),
+                execute: eaccess(&path, libc::X_OK).is_ok(),
+            };
+            assert_eq!(effective, computed(&path), "mode {mode:o}");
+        }
+        assert_eq!(
+            Permissions::new(false, false, false),
+            computed(&tempdir.path().join("missing"))
+        );
+
+        let dir = tempdir.path().join("private");
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let effective = Permissions {
+            read: eaccess(&path, libc::R_OK).is_ok(),
+            write: eaccess(&path, libc::W_OK).is_ok(),
+            execute: eaccess(&path, libc::X_OK).is_ok(),
+        };
+        let result = computed(&path);
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(effective, result, "parent mode 600");
+    }
+}
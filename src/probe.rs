@@ -0,0 +1,445 @@
+//! Verifies permissions by trying them
+//!
+//! Permission checks can be wrong. Landlock, seccomp, AppArmor, and read-only bind mounts
+//! restrict access in ways that `faccessat` and mode bits don't always see. A probe tries
+//! non-destructive operations and reports where the result disagrees with the checked
+//! permissions. Probing is opt-in because it opens files and, when asked, creates one.
+use crate::{path_facts::PathFacts, path_state::Existence, resolved_metadata::ResolvedType};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// Which operations a probe may try
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProbeScope {
+    /// Only open the path and list directories, nothing on disk changes
+    ReadOnly,
+    /// Also create and immediately delete a uniquely named file in the parent directory
+    CreateInParent,
+}
+
+/// An operation tried by a probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum ProbeOperation {
+    /// `open` with `O_PATH`, succeeds when the path can be looked up (Linux only)
+    OpenPath,
+    /// `open` with `O_RDONLY`, checks read on a file
+    OpenRead,
+    /// `opendir`, checks read on a directory
+    OpenDir,
+    /// Create and delete a temp file, checks write on a directory
+    CreateTempFile,
+}
+
+impl ProbeOperation {
+    fn verb(&self) -> &'static str {
+        match self {
+            ProbeOperation::OpenPath => "open",
+            ProbeOperation::OpenRead => "open",
+            ProbeOperation::OpenDir => "opendir",
+            ProbeOperation::CreateTempFile => "create",
+        }
+    }
+
+    /// What the permission check claims when it expects success
+    fn claim(&self, expected: bool) -> &'static str {
+        match (self, expected) {
+            (ProbeOperation::OpenPath, true) => "it exists",
+            (ProbeOperation::OpenPath, false) => "it is missing",
+            (ProbeOperation::CreateTempFile, true) => "writable",
+            (ProbeOperation::CreateTempFile, false) => "not writable",
+            (_, true) => "readable",
+            (_, false) => "not readable",
+        }
+    }
+}
+
+impl Display for ProbeOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbeOperation::OpenPath => write!(f, "open with `O_PATH`"),
+            ProbeOperation::OpenRead => write!(f, "open with `O_RDONLY`"),
+            ProbeOperation::OpenDir => write!(f, "opendir"),
+            ProbeOperation::CreateTempFile => write!(f, "create and delete a temp file"),
+        }
+    }
+}
+
+/// The result of a single probe
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Probe {
+    pub(crate) operation: ProbeOperation,
+    pub(crate) path: PathBuf,
+    /// What the permission check says should happen, `None` when it has no opinion
+    pub(crate) expected: Option<bool>,
+    pub(crate) succeeded: bool,
+    pub(crate) raw_os_error: Option<i32>,
+    pub(crate) message: Option<String>,
+}
+
+impl Probe {
+    fn new(
+        operation: ProbeOperation,
+        path: &Path,
+        expected: Option<bool>,
+        result: Result<(), std::io::Error>,
+    ) -> Self {
+        let (raw_os_error, message) = match &result {
+            Ok(()) => (None, None),
+            Err(error) => (error.raw_os_error(), Some(error.to_string())),
+        };
+        Probe {
+            operation,
+            path: path.to_path_buf(),
+            expected,
+            succeeded: result.is_ok(),
+            raw_os_error,
+            message,
+        }
+    }
+
+    /// The operation that was tried
+    pub fn operation(&self) -> ProbeOperation {
+        self.operation
+    }
+
+    /// The path the operation was tried on
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True when the operation succeeded
+    pub fn succeeded(&self) -> bool {
+        self.succeeded
+    }
+
+    /// The errno of a failed operation
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.raw_os_error
+    }
+
+    /// True when the result is different from what the permission check says
+    pub fn disagrees(&self) -> bool {
+        self.expected
+            .is_some_and(|expected| expected != self.succeeded)
+    }
+
+    /// Describes the disagreement, such as "permissions say writable, but create failed with EROFS"
+    pub fn disagreement(&self) -> Option<String> {
+        let expected = self.expected?;
+        if !self.disagrees() {
+            return None;
+        }
+        let verb = self.operation.verb();
+        let claim = self.operation.claim(expected);
+        Some(if self.succeeded {
+            format!("permissions say {claim}, but {verb} succeeded")
+        } else {
+            format!(
+                "permissions say {claim}, but {verb} failed with {errno}",
+                errno = self.errno_name()
+            )
+        })
+    }
+
+    fn errno_name(&self) -> String {
+        match self.raw_os_error {
+            Some(errno) => errno_name(errno)
+                .map(String::from)
+                .unwrap_or_else(|| format!("errno {errno}")),
+            None => "an error".to_string(),
+        }
+    }
+}
+
+impl Display for Probe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{operation} `{path}`: ",
+            operation = self.operation,
+            path = self.path.display()
+        )?;
+        if self.succeeded {
+            write!(f, "ok")?;
+        } else {
+            write!(f, "failed with {}", self.errno_name())?;
+            if let Some(message) = &self.message {
+                write!(f, " ({message})")?;
+            }
+        }
+        if let Some(disagreement) = self.disagreement() {
+            write!(f, " <- {disagreement}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every probe tried for a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeReport {
+    pub(crate) probes: Vec<Probe>,
+}
+
+impl ProbeReport {
+    /// Tries operations on the path and its parent, comparing them to the gathered facts
+    pub(crate) fn new(facts: &PathFacts, scope: ProbeScope) -> Self {
+        let mut probes = vec![];
+        let Some(absolute) = facts.absolute() else {
+            return ProbeReport { probes };
+        };
+        let read = facts.permissions().map(|permissions| permissions.read);
+
+        if cfg!(target_os = "linux") {
+            let exists = match facts.existence() {
+                Existence::Exists => Some(true),
+                Existence::Missing => Some(false),
+                Existence::Unknown(_) => None,
+            };
+            probes.push(Probe::new(
+                ProbeOperation::OpenPath,
+                absolute,
+                exists,
+                open_path(absolute),
+            ));
+        }
+        match facts.resolved_type() {
+            Some(ResolvedType::Dir) => probes.push(Probe::new(
+                ProbeOperation::OpenDir,
+                absolute,
+                read,
+                open_dir(absolute),
+            )),
+            // Opening devices can have side effects and sockets can't be opened
+            Some(ResolvedType::File) | Some(ResolvedType::Fifo) => probes.push(Probe::new(
+                ProbeOperation::OpenRead,
+                absolute,
+                read,
+                open_read(absolute),
+            )),
+            _ => {}
+        }
+
+        if let Some(parent) = absolute.parent() {
+            let parent_permissions = facts.parent_permissions();
+            probes.push(Probe::new(
+                ProbeOperation::OpenDir,
+                parent,
+                parent_permissions.map(|permissions| permissions.read),
+                open_dir(parent),
+            ));
+            if scope == ProbeScope::CreateInParent {
+                probes.push(Probe::new(
+                    ProbeOperation::CreateTempFile,
+                    parent,
+                    parent_permissions.map(|permissions| permissions.write),
+                    create_temp_file(parent),
+                ));
+            }
+        }
+        ProbeReport { probes }
+    }
+
+    /// Every probe in the order they were tried
+    pub fn probes(&self) -> &[Probe] {
+        &self.probes
+    }
+
+    /// Probes whose result is different from what the permission check says
+    pub fn disagreements(&self) -> impl Iterator<Item = &Probe> {
+        self.probes.iter().filter(|probe| probe.disagrees())
+    }
+}
+
+impl Display for ProbeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Probes:")?;
+        for probe in &self.probes {
+            writeln!(f, "{}", crate::style::bullet(probe.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_path(path: &Path) -> Result<(), std::io::Error> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH)
+        .open(path)
+        .map(drop)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_path(_path: &Path) -> Result<(), std::io::Error> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+fn open_read(path: &Path) -> Result<(), std::io::Error> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        // Don't wait for a writer when the path is a FIFO
+        options.custom_flags(libc::O_NONBLOCK | libc::O_NOCTTY);
+    }
+    options.open(path).map(drop)
+}
+
+fn open_dir(path: &Path) -> Result<(), std::io::Error> {
+    std::fs::read_dir(path).map(drop)
+}
+
+/// Creates a uniquely named file and deletes it right away
+fn create_temp_file(dir: &Path) -> Result<(), std::io::Error> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or_default();
+    let path = dir.join(format!(
+        ".path_facts_probe_{pid}_{nanos}",
+        pid = std::process::id()
+    ));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)
+}
+
+/// Symbolic name of common errno values, such as `EROFS`
+fn errno_name(errno: i32) -> Option<&'static str> {
+    #[cfg(unix)]
+    {
+        let name = match errno {
+            libc::EPERM => "EPERM",
+            libc::ENOENT => "ENOENT",
+            libc::EIO => "EIO",
+            libc::ENXIO => "ENXIO",
+            libc::EBADF => "EBADF",
+            libc::EAGAIN => "EAGAIN",
+            libc::EACCES => "EACCES",
+            libc::EBUSY => "EBUSY",
+            libc::EEXIST => "EEXIST",
+            libc::EXDEV => "EXDEV",
+            libc::ENODEV => "ENODEV",
+            libc::ENOTDIR => "ENOTDIR",
+            libc::EISDIR => "EISDIR",
+            libc::EINVAL => "EINVAL",
+            libc::ENFILE => "ENFILE",
+            libc::EMFILE => "EMFILE",
+            libc::ETXTBSY => "ETXTBSY",
+            libc::EFBIG => "EFBIG",
+            libc::ENOSPC => "ENOSPC",
+            libc::EROFS => "EROFS",
+            libc::EMLINK => "EMLINK",
+            libc::ENAMETOOLONG => "ENAMETOOLONG",
+            libc::ENOSYS => "ENOSYS",
+            libc::ELOOP => "ELOOP",
+            libc::EDQUOT => "EDQUOT",
+            libc::ESTALE => "ESTALE",
+            libc::EOPNOTSUPP => "EOPNOTSUPP",
+            _ => return None,
+        };
+        Some(name)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = errno;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_probe_agrees() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let facts = PathFacts::new(&path).probe(ProbeScope::CreateInParent);
+        let report = facts.probe_report().unwrap();
+        let operations = report
+            .probes()
+            .iter()
+            .map(|probe| (probe.operation(), probe.path().to_path_buf()))
+            .collect::<Vec<_>>();
+        let mut expected = vec![
+            (ProbeOperation::OpenRead, path.clone()),
+            (ProbeOperation::OpenDir, tempdir.path().to_path_buf()),
+            (ProbeOperation::CreateTempFile, tempdir.path().to_path_buf()),
+        ];
+        if cfg!(target_os = "linux") {
+            expected.insert(0, (ProbeOperation::OpenPath, path.clone()));
+        }
+        assert_eq!(expected, operations);
+        assert_eq!(0, report.disagreements().count());
+        // The temp file is removed again
+        assert_eq!(1, std::fs::read_dir(tempdir.path()).unwrap().count());
+        let rendered = facts.to_string();
+        assert!(rendered.contains(" - Probes:\n"));
+        assert!(rendered.contains(&format!(
+            "    - open with `O_RDONLY` `{}`: ok\n",
+            path.display()
+        )));
+
+        let facts = PathFacts::new(&path).probe(ProbeScope::ReadOnly);
+        assert!(facts
+            .probe_report()
+            .unwrap()
+            .probes()
+            .iter()
+            .all(|probe| probe.operation() != ProbeOperation::CreateTempFile));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disagreement() {
+        let probe = Probe::new(
+            ProbeOperation::CreateTempFile,
+            Path::new("/app"),
+            Some(true),
+            Err(std::io::Error::from_raw_os_error(libc::EROFS)),
+        );
+        assert!(probe.disagrees());
+        assert_eq!(
+            Some("permissions say writable, but create failed with EROFS".to_string()),
+            probe.disagreement()
+        );
+        assert_eq!(
+            "create and delete a temp file `/app`: failed with EROFS (Read-only file system (os error 30)) <- permissions say writable, but create failed with EROFS",
+            probe.to_string()
+        );
+
+        let probe = Probe::new(
+            ProbeOperation::OpenRead,
+            Path::new("/app/file"),
+            Some(false),
+            Ok(()),
+        );
+        assert_eq!(
+            Some("permissions say not readable, but open succeeded".to_string()),
+            probe.disagreement()
+        );
+
+        let probe = Probe::new(ProbeOperation::OpenDir, Path::new("/app"), None, Ok(()));
+        assert!(!probe.disagrees());
+    }
+}
@@ -6,11 +6,55 @@
 
 use std::{fmt::Display, fs::Metadata, path::Path};
 
-/// Indicates the path is a file or directory or it's a valid simlink to a file or directory
-#[derive(Debug)]
-pub(crate) enum ResolvedType {
+/// Indicates the type of a path, or of what a valid symlink points to
+///
+/// Anything that is not a directory is not necessarily a regular file. Reading a FIFO blocks
+/// until something writes to it, and devices and sockets behave differently than files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResolvedType {
     File,
     Dir,
+    /// Only when describing the link itself, such as metadata from `std::fs::symlink_metadata`
+    Symlink,
+    /// Named pipe
+    Fifo,
+    Socket,
+    BlockDevice(Device),
+    CharDevice(Device),
+}
+
+/// Device number and name of a block or character device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub(crate) major: u32,
+    pub(crate) minor: u32,
+    pub(crate) name: Option<String>,
+}
+
+impl Device {
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Kernel name of the device such as `sda` or `null`, from `/sys/dev` on Linux
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl Display for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.major, self.minor)?;
+        if let Some(name) = &self.name {
+            write!(f, " `{name}`")?;
+        }
+        Ok(())
+    }
 }
 
 impl Display for ResolvedType {
@@ -18,10 +62,124 @@ impl Display for ResolvedType {
         match self {
             ResolvedType::File => write!(f, "file"),
             ResolvedType::Dir => write!(f, "directory"),
+            ResolvedType::Symlink => write!(f, "symlink"),
+            ResolvedType::Fifo => write!(f, "fifo"),
+            ResolvedType::Socket => write!(f, "socket"),
+            ResolvedType::BlockDevice(device) => write!(f, "block device {device}"),
+            ResolvedType::CharDevice(device) => write!(f, "character device {device}"),
         }
     }
 }
 
+impl ResolvedType {
+    pub(crate) fn is_dir(&self) -> bool {
+        self == &ResolvedType::Dir
+    }
+
+    /// Type of the given metadata, `Symlink` is only possible from `symlink_metadata`
+    pub(crate) fn from_metadata(metadata: &Metadata) -> Self {
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            return ResolvedType::Dir;
+        }
+        if file_type.is_symlink() {
+            return ResolvedType::Symlink;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return ResolvedType::Fifo;
+            }
+            if file_type.is_socket() {
+                return ResolvedType::Socket;
+            }
+            if file_type.is_block_device() {
+                return ResolvedType::BlockDevice(device(metadata, "block"));
+            }
+            if file_type.is_char_device() {
+                return ResolvedType::CharDevice(device(metadata, "char"));
+            }
+        }
+        ResolvedType::File
+    }
+}
+
+#[cfg(unix)]
+fn device(metadata: &Metadata, class: &str) -> Device {
+    use std::os::unix::fs::MetadataExt;
+    let (major, minor) = split_rdev(metadata.rdev());
+
+    Device {
+        major,
+        minor,
+        name: sys_dev_name(class, major, minor),
+    }
+}
+
+/// Major and minor numbers of a device, encoded the way glibc and the kernel do on Linux
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn split_rdev(rdev: u64) -> (u32, u32) {
+    let major = ((rdev >> 32) & 0xffff_f000) | ((rdev >> 8) & 0x0000_0fff);
+    let minor = ((rdev >> 12) & 0xffff_ff00) | (rdev & 0x0000_00ff);
+    (major as u32, minor as u32)
+}
+
+/// Major and minor numbers of a device, the encoding differs between platforms
+#[cfg(all(
+    unix,
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))
+))]
+fn split_rdev(rdev: u64) -> (u32, u32) {
+    let rdev = rdev as libc::dev_t;
+    (libc::major(rdev) as u32, libc::minor(rdev) as u32)
+}
+
+/// Major and minor numbers of a device, libc only declares them `unsafe` on illumos and Solaris
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+fn split_rdev(rdev: u64) -> (u32, u32) {
+    let rdev = rdev as libc::dev_t;
+    // SAFETY: Only splits the bits of the device number, no memory is accessed
+    let (major, minor) = unsafe { (libc::major(rdev), libc::minor(rdev)) };
+    (major as u32, minor as u32)
+}
+
+/// Reads the device name from `/sys/dev/{block,char}/{major}:{minor}`
+///
+/// Prefers `DEVNAME` from `uevent` (the name under `/dev`) and falls back to the name of
+/// the device directory the entry links to.
+#[cfg(target_os = "linux")]
+fn sys_dev_name(class: &str, major: u32, minor: u32) -> Option<String> {
+    let entry = Path::new("/sys/dev")
+        .join(class)
+        .join(format!("{major}:{minor}"));
+    std::fs::read_to_string(entry.join("uevent"))
+        .ok()
+        .and_then(|uevent| {
+            uevent
+                .lines()
+                .find_map(|line| line.strip_prefix("DEVNAME="))
+                .map(str::to_string)
+        })
+        .or_else(|| {
+            std::fs::read_link(&entry).ok().and_then(|target| {
+                target
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+        })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn sys_dev_name(_class: &str, _major: u32, _minor: u32) -> Option<String> {
+    None
+}
+
 pub(crate) struct ResolvedMetadata(Metadata);
 
 impl ResolvedMetadata {
@@ -30,10 +188,59 @@ impl ResolvedMetadata {
     }
 
     pub(crate) fn resolved_type(&self) -> ResolvedType {
-        if self.0.is_dir() {
-            ResolvedType::Dir
-        } else {
-            ResolvedType::File
+        ResolvedType::from_metadata(&self.0)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_split_rdev() {
+        assert_eq!((1, 3), split_rdev(0x0103));
+        assert_eq!((0x12345, 0x6789a), split_rdev(0x1_2000_6783_459a));
+    }
+
+    #[test]
+    fn test_fifo_socket_and_devices() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let fifo = tempdir.path().join("fifo");
+        let c_path = std::ffi::CString::new(fifo.as_os_str().as_encoded_bytes()).unwrap();
+        // SAFETY: `c_path` is a valid nul terminated string
+        assert_eq!(0, unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) });
+        assert_eq!(
+            ResolvedType::Fifo,
+            ResolvedMetadata::new(&fifo).unwrap().resolved_type()
+        );
+
+        let socket = tempdir.path().join("socket");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        assert_eq!(
+            ResolvedType::Socket,
+            ResolvedMetadata::new(&socket).unwrap().resolved_type()
+        );
+
+        let link = tempdir.path().join("link");
+        std::os::unix::fs::symlink(&fifo, &link).unwrap();
+        let metadata = std::fs::symlink_metadata(&link).unwrap();
+        assert_eq!(
+            ResolvedType::Symlink,
+            ResolvedType::from_metadata(&metadata)
+        );
+
+        let ResolvedType::CharDevice(device) =
+            ResolvedMetadata::new("/dev/null").unwrap().resolved_type()
+        else {
+            panic!("Expected /dev/null to be a character device");
+        };
+        assert_eq!((1, 3), (device.major(), device.minor()));
+        #[cfg(target_os = "linux")]
+        if Path::new("/sys/dev/char/1:3").exists() {
+            assert_eq!(Some("null"), device.name());
         }
     }
 }
@@ -0,0 +1,768 @@
+//! Versioned serde schema for `PathFacts` (requires the `serde` feature)
+//!
+//! Internal types hold values such as `std::io::Error` that cannot be serialized, and
+//! we don't want the on-disk format to change every time an internal type changes.
+//! These types mirror the gathered state in a stable shape. Bump `SCHEMA_VERSION`
+//! whenever the shape changes in a way that older readers cannot understand.
+//!
+//! Errors are stored as their kind, raw OS error number, and message. When deserialized
+//! they become an `std::io::Error` with the same kind that displays the original message,
+//! so facts captured on one machine render the same on another.
+use crate::{
+    abs_path::{AbsPath, AbsPathError},
+    canonical_path::CanonicalPath,
+    capacity::Capacity,
+    error_facts::ErrorFacts,
+    happy_path::{DirOk, HappyPath, LookedUp, SearchOnlyDir, UnhappyPath},
+    inode_flags::InodeFlags,
+    labels::LabelFacts,
+    length_limits::LengthLimits,
+    mounts::MountFacts,
+    operation::{Blocker, Operation, OperationFacts},
+    ownership::Ownership,
+    path_facts::PathFacts,
+    permissions::{AccessCheck, Permissions},
+    probe::ProbeReport,
+    resolved_metadata,
+    symlink_chain::SymlinkChain,
+    user_access::UserAccess,
+};
+use serde::{Deserialize, Serialize};
+use std::{io::ErrorKind, path::PathBuf};
+
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+impl Serialize for PathFacts {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Versioned {
+            version: SCHEMA_VERSION,
+            facts: Facts::from(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PathFacts {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let Versioned { version, facts } = Versioned::deserialize(deserializer)?;
+        if version == SCHEMA_VERSION {
+            Ok(PathFacts::from(facts))
+        } else {
+            Err(serde::de::Error::custom(format!(
+                "unsupported path_facts schema version {version}, expected {SCHEMA_VERSION}"
+            )))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Versioned {
+    version: u32,
+    #[serde(flatten)]
+    facts: Facts,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Facts {
+    path: PathBuf,
+    state: State,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reported_error: Option<ErrorFacts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    operation: Option<OperationSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    as_user: Option<UserAccess>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access_check: Option<AccessCheck>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    probe: Option<ProbeReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    labels: Option<LabelFacts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mounts: Option<MountFacts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    capacity: Option<Capacity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    length_limits: Option<LengthLimits>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OperationSchema {
+    operation: Operation,
+    blockers: Vec<Blocker>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum State {
+    Exists {
+        absolute: PathBuf,
+        canonical: PathBuf,
+        symlink_target: Option<PathBuf>,
+        resolved_type: ResolvedType,
+        permissions: Permissions,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ownership: Option<Ownership>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flags: Option<InodeFlags>,
+        parent: Dir,
+    },
+    EmptyPath,
+    CannotReadCwd {
+        error: IoError,
+    },
+    IsRoot {
+        absolute: PathBuf,
+    },
+    ParentProblem {
+        absolute: PathBuf,
+        parent: PathBuf,
+        error: IoError,
+        #[serde(default)]
+        ancestors: Vec<AncestorError>,
+        prior: Box<Facts>,
+    },
+    DoesNotExist {
+        absolute: PathBuf,
+        parent: Dir,
+    },
+    ParentNotListable {
+        absolute: PathBuf,
+        parent: SearchOnly,
+        error: IoError,
+        target: Option<Target>,
+    },
+    CannotCanonicalize {
+        absolute: PathBuf,
+        parent: Dir,
+        error: IoError,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        symlink_chain: Option<SymlinkChain>,
+    },
+    CannotMetadata {
+        absolute: PathBuf,
+        canonical: PathBuf,
+        parent: Dir,
+        error: IoError,
+    },
+    CannotReadLink {
+        absolute: PathBuf,
+        canonical: PathBuf,
+        parent: Dir,
+        error: IoError,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct AncestorError {
+    path: PathBuf,
+    error: IoError,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Dir {
+    absolute: PathBuf,
+    canonical: PathBuf,
+    entries: Vec<PathBuf>,
+    permissions: Permissions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ownership: Option<Ownership>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    flags: Option<InodeFlags>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SearchOnly {
+    absolute: PathBuf,
+    permissions: Permissions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    flags: Option<InodeFlags>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Target {
+    canonical: PathBuf,
+    symlink_target: Option<PathBuf>,
+    resolved_type: ResolvedType,
+    permissions: Permissions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ownership: Option<Ownership>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    flags: Option<InodeFlags>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ResolvedType {
+    File,
+    Dir,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice(Device),
+    CharDevice(Device),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Device {
+    major: u32,
+    minor: u32,
+    name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IoError {
+    kind: String,
+    raw_os_error: Option<i32>,
+    message: String,
+}
+
+/// Error payload for an `std::io::Error` that was deserialized
+///
+/// Preserves the raw OS error so that serializing it again is lossless.
+#[derive(Debug)]
+struct RecordedError {
+    raw_os_error: Option<i32>,
+    message: String,
+}
+
+impl std::fmt::Display for RecordedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RecordedError {}
+
+impl From<&PathFacts> for Facts {
+    fn from(facts: &PathFacts) -> Self {
+        let state = match facts.state.as_ref().map_err(|e| &**e) {
+            Ok(happy) => State::Exists {
+                absolute: path(&happy.absolute),
+                canonical: path(&happy.canonical),
+                symlink_target: happy.symlink_target.as_ref().map(path),
+                resolved_type: ResolvedType::from(&happy.resolved_type),
+                permissions: happy.permissions,
+                ownership: happy.ownership.clone(),
+                flags: happy.flags,
+                parent: Dir::from(&happy.parent),
+            },
+            Err(UnhappyPath::AbsPathError(AbsPathError::PathIsEmpty(_))) => State::EmptyPath,
+            Err(UnhappyPath::AbsPathError(AbsPathError::CannotReadCWD(_, error))) => {
+                State::CannotReadCwd {
+                    error: IoError::from(error),
+                }
+            }
+            Err(UnhappyPath::IsRoot(absolute)) => State::IsRoot {
+                absolute: path(absolute),
+            },
+            Err(UnhappyPath::ParentProblem {
+                absolute,
+                parent,
+                error,
+                ancestors,
+                prior,
+            }) => State::ParentProblem {
+                absolute: path(absolute),
+                parent: path(parent),
+                error: IoError::from(error),
+                ancestors: ancestors
+                    .iter()
+                    .map(|(ancestor, error)| AncestorError {
+                        path: path(ancestor),
+                        error: IoError::from(error),
+                    })
+                    .collect(),
+                prior: Box::new(Facts::from(&**prior)),
+            },
+            Err(UnhappyPath::DoesNotExist { absolute, parent }) => State::DoesNotExist {
+                absolute: path(absolute),
+                parent: Dir::from(parent),
+            },
+            Err(UnhappyPath::ParentNotListable {
+                absolute,
+                parent,
+                error,
+                target,
+            }) => State::ParentNotListable {
+                absolute: path(absolute),
+                parent: SearchOnly {
+                    absolute: path(&parent.absolute),
+                    permissions: parent.permissions,
+                    flags: parent.flags,
+                },
+                error: IoError::from(error),
+                target: target.as_ref().map(|target| Target {
+                    canonical: path(&target.canonical),
+                    symlink_target: target.symlink_target.as_ref().map(path),
+                    resolved_type: ResolvedType::from(&target.resolved_type),
+                    permissions: target.permissions,
+                    ownership: target.ownership.clone(),
+                    flags: target.flags,
+                }),
+            },
+            Err(UnhappyPath::CannotCanonicalize {
+                absolute,
+                parent,
+                error,
+                symlink_chain,
+            }) => State::CannotCanonicalize {
+                absolute: path(absolute),
+                parent: Dir::from(parent),
+                error: IoError::from(error),
+                symlink_chain: symlink_chain.clone(),
+            },
+            Err(UnhappyPath::CannotMetadata {
+                absolute,
+                canonical,
+                parent,
+                error,
+            }) => State::CannotMetadata {
+                absolute: path(absolute),
+                canonical: path(canonical),
+                parent: Dir::from(parent),
+                error: IoError::from(error),
+            },
+            Err(UnhappyPath::CannotReadLink {
+                absolute,
+                canonical,
+                parent,
+                error,
+            }) => State::CannotReadLink {
+                absolute: path(absolute),
+                canonical: path(canonical),
+                parent: Dir::from(parent),
+                error: IoError::from(error),
+            },
+        };
+
+        Facts {
+            path: facts.path.clone(),
+            state,
+            reported_error: facts.reported_error.clone(),
+            operation: facts.operation.as_ref().map(|facts| OperationSchema {
+                operation: facts.operation,
+                blockers: facts.blockers.clone(),
+            }),
+            as_user: facts.as_user.clone(),
+            access_check: facts.access_check,
+            probe: facts.probe.clone(),
+            labels: facts.labels.clone(),
+            mounts: facts.mounts.clone(),
+            capacity: facts.capacity.clone(),
+            length_limits: facts.length_limits.clone(),
+        }
+    }
+}
+
+impl From<Facts> for PathFacts {
+    fn from(facts: Facts) -> Self {
+        let Facts {
+            path,
+            state,
+            reported_error,
+            operation,
+            as_user,
+            access_check,
+            probe,
+            labels,
+            mounts,
+            capacity,
+            length_limits,
+        } = facts;
+        let state = match state {
+            State::Exists {
+                absolute,
+                canonical,
+                symlink_target,
+                resolved_type,
+                permissions,
+                ownership,
+                flags,
+                parent,
+            } => Ok(HappyPath {
+                absolute: AbsPath::from_recorded(absolute),
+                canonical: CanonicalPath::from_recorded(canonical),
+                symlink_target: symlink_target.map(AbsPath::from_recorded),
+                resolved_type: resolved_type.into(),
+                parent: parent.into(),
+                permissions,
+                ownership,
+                flags,
+            }),
+            State::EmptyPath => Err(UnhappyPath::AbsPathError(AbsPathError::PathIsEmpty(
+                path.clone(),
+            ))),
+            State::CannotReadCwd { error } => Err(UnhappyPath::AbsPathError(
+                AbsPathError::CannotReadCWD(path.clone(), error.into()),
+            )),
+            State::IsRoot { absolute } => {
+                Err(UnhappyPath::IsRoot(AbsPath::from_recorded(absolute)))
+            }
+            State::ParentProblem {
+                absolute,
+                parent,
+                error,
+                ancestors,
+                prior,
+            } => Err(UnhappyPath::ParentProblem {
+                absolute: AbsPath::from_recorded(absolute),
+                parent: AbsPath::from_recorded(parent),
+                error: error.into(),
+                ancestors: ancestors
+                    .into_iter()
+                    .map(|ancestor| (AbsPath::from_recorded(ancestor.path), ancestor.error.into()))
+                    .collect(),
+                prior: Box::new(PathFacts::from(*prior)),
+            }),
+            State::DoesNotExist { absolute, parent } => Err(UnhappyPath::DoesNotExist {
+                absolute: AbsPath::from_recorded(absolute),
+                parent: parent.into(),
+            }),
+            State::ParentNotListable {
+                absolute,
+                parent,
+                error,
+                target,
+            } => Err(UnhappyPath::ParentNotListable {
+                absolute: AbsPath::from_recorded(absolute),
+                parent: SearchOnlyDir {
+                    absolute: AbsPath::from_recorded(parent.absolute),
+                    permissions: parent.permissions,
+                    flags: parent.flags,
+                },
+                error: error.into(),
+                target: target.map(|target| LookedUp {
+                    canonical: CanonicalPath::from_recorded(target.canonical),
+                    symlink_target: target.symlink_target.map(AbsPath::from_recorded),
+                    resolved_type: target.resolved_type.into(),
+                    permissions: target.permissions,
+                    ownership: target.ownership,
+                    flags: target.flags,
+                }),
+            }),
+            State::CannotCanonicalize {
+                absolute,
+                parent,
+                error,
+                symlink_chain,
+            } => Err(UnhappyPath::CannotCanonicalize {
+                absolute: AbsPath::from_recorded(absolute),
+                parent: parent.into(),
+                error: error.into(),
+                symlink_chain,
+            }),
+            State::CannotMetadata {
+                absolute,
+                canonical,
+                parent,
+                error,
+            } => Err(UnhappyPath::CannotMetadata {
+                absolute: AbsPath::from_recorded(absolute),
+                canonical: CanonicalPath::from_recorded(canonical),
+                parent: parent.into(),
+                error: error.into(),
+            }),
+            State::CannotReadLink {
+                absolute,
+                canonical,
+                parent,
+                error,
+            } => Err(UnhappyPath::CannotReadLink {
+                absolute: AbsPath::from_recorded(absolute),
+                canonical: CanonicalPath::from_recorded(canonical),
+                parent: parent.into(),
+                error: error.into(),
+            }),
+        };
+
+        PathFacts {
+            path,
+            state: state.map_err(Box::new),
+            reported_error,
+            operation: operation.map(|facts| OperationFacts {
+                operation: facts.operation,
+                blockers: facts.blockers,
+            }),
+            as_user,
+            access_check,
+            probe,
+            labels,
+            mounts,
+            capacity,
+            length_limits,
+        }
+    }
+}
+
+fn path(path: impl AsRef<std::path::Path>) -> PathBuf {
+    path.as_ref().to_path_buf()
+}
+
+impl From<&DirOk> for Dir {
+    fn from(dir: &DirOk) -> Self {
+        Dir {
+            absolute: path(&dir.absolute),
+            canonical: path(&dir.canonical),
+            entries: dir.entries.iter().map(path).collect(),
+            permissions: dir.permissions,
+            ownership: dir.ownership.clone(),
+            flags: dir.flags,
+        }
+    }
+}
+
+impl From<Dir> for DirOk {
+    fn from(dir: Dir) -> Self {
+        DirOk {
+            absolute: AbsPath::from_recorded(dir.absolute),
+            canonical: CanonicalPath::from_recorded(dir.canonical),
+            entries: dir
+                .entries
+                .into_iter()
+                .map(AbsPath::from_recorded)
+                .collect(),
+            permissions: dir.permissions,
+            ownership: dir.ownership,
+            flags: dir.flags,
+        }
+    }
+}
+
+impl From<&resolved_metadata::ResolvedType> for ResolvedType {
+    fn from(resolved_type: &resolved_metadata::ResolvedType) -> Self {
+        match resolved_type {
+            resolved_metadata::ResolvedType::File => ResolvedType::File,
+            resolved_metadata::ResolvedType::Dir => ResolvedType::Dir,
+            resolved_metadata::ResolvedType::Symlink => ResolvedType::Symlink,
+            resolved_metadata::ResolvedType::Fifo => ResolvedType::Fifo,
+            resolved_metadata::ResolvedType::Socket => ResolvedType::Socket,
+            resolved_metadata::ResolvedType::BlockDevice(device) => {
+                ResolvedType::BlockDevice(Device::from(device))
+            }
+            resolved_metadata::ResolvedType::CharDevice(device) => {
+                ResolvedType::CharDevice(Device::from(device))
+            }
+        }
+    }
+}
+
+impl From<ResolvedType> for resolved_metadata::ResolvedType {
+    fn from(resolved_type: ResolvedType) -> Self {
+        match resolved_type {
+            ResolvedType::File => resolved_metadata::ResolvedType::File,
+            ResolvedType::Dir => resolved_metadata::ResolvedType::Dir,
+            ResolvedType::Symlink => resolved_metadata::ResolvedType::Symlink,
+            ResolvedType::Fifo => resolved_metadata::ResolvedType::Fifo,
+            ResolvedType::Socket => resolved_metadata::ResolvedType::Socket,
+            ResolvedType::BlockDevice(device) => {
+                resolved_metadata::ResolvedType::BlockDevice(device.into())
+            }
+            ResolvedType::CharDevice(device) => {
+                resolved_metadata::ResolvedType::CharDevice(device.into())
+            }
+        }
+    }
+}
+
+impl From<&resolved_metadata::Device> for Device {
+    fn from(device: &resolved_metadata::Device) -> Self {
+        Device {
+            major: device.major,
+            minor: device.minor,
+            name: device.name.clone(),
+        }
+    }
+}
+
+impl From<Device> for resolved_metadata::Device {
+    fn from(device: Device) -> Self {
+        resolved_metadata::Device {
+            major: device.major,
+            minor: device.minor,
+            name: device.name,
+        }
+    }
+}
+
+impl From<&std::io::Error> for IoError {
+    fn from(error: &std::io::Error) -> Self {
+        let raw_os_error = error.raw_os_error().or_else(|| {
+            error
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<RecordedError>())
+                .and_then(|recorded| recorded.raw_os_error)
+        });
+        IoError {
+            kind: format!("{:?}", error.kind()),
+            raw_os_error,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<IoError> for std::io::Error {
+    fn from(error: IoError) -> Self {
+        let IoError {
+            kind,
+            raw_os_error,
+            message,
+        } = error;
+        std::io::Error::new(
+            error_kind(&kind),
+            RecordedError {
+                raw_os_error,
+                message,
+            },
+        )
+    }
+}
+
+/// Serializes an `ErrorKind` by name, for use with `#[serde(with = "...")]`
+pub(crate) mod error_kind {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::io::ErrorKind;
+
+    pub(crate) fn serialize<S: Serializer>(
+        kind: &ErrorKind,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{kind:?}"))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ErrorKind, D::Error> {
+        String::deserialize(deserializer).map(|name| super::error_kind(&name))
+    }
+}
+
+/// Parses the `Debug` representation of an `ErrorKind`
+///
+/// Kinds that are unknown to this version of Rust become `ErrorKind::Other`, the
+/// original name is still preserved in the message.
+fn error_kind(name: &str) -> ErrorKind {
+    [
+        ErrorKind::NotFound,
+        ErrorKind::PermissionDenied,
+        ErrorKind::ConnectionRefused,
+        ErrorKind::ConnectionReset,
+        ErrorKind::HostUnreachable,
+        ErrorKind::NetworkUnreachable,
+        ErrorKind::ConnectionAborted,
+        ErrorKind::NotConnected,
+        ErrorKind::AddrInUse,
+        ErrorKind::AddrNotAvailable,
+        ErrorKind::NetworkDown,
+        ErrorKind::BrokenPipe,
+        ErrorKind::AlreadyExists,
+        ErrorKind::WouldBlock,
+        ErrorKind::NotADirectory,
+        ErrorKind::IsADirectory,
+        ErrorKind::DirectoryNotEmpty,
+        ErrorKind::ReadOnlyFilesystem,
+        ErrorKind::StaleNetworkFileHandle,
+        ErrorKind::InvalidInput,
+        ErrorKind::InvalidData,
+        ErrorKind::TimedOut,
+        ErrorKind::WriteZero,
+        ErrorKind::StorageFull,
+        ErrorKind::NotSeekable,
+        ErrorKind::QuotaExceeded,
+        ErrorKind::FileTooLarge,
+        ErrorKind::ResourceBusy,
+        ErrorKind::ExecutableFileBusy,
+        ErrorKind::Deadlock,
+        ErrorKind::CrossesDevices,
+        ErrorKind::TooManyLinks,
+        ErrorKind::InvalidFilename,
+        ErrorKind::ArgumentListTooLong,
+        ErrorKind::Interrupted,
+        ErrorKind::Unsupported,
+        ErrorKind::UnexpectedEof,
+        ErrorKind::OutOfMemory,
+    ]
+    .into_iter()
+    .find(|kind| format!("{kind:?}") == name)
+    .unwrap_or(ErrorKind::Other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    fn round_trip(facts: &PathFacts) -> PathFacts {
+        let json = serde_json::to_string(facts).unwrap();
+        let recorded: PathFacts = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&recorded).unwrap());
+        recorded
+    }
+
+    #[test]
+    fn test_round_trip_exists() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("exists.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let facts = PathFacts::new(&path);
+        let recorded = round_trip(&facts);
+        assert_eq!(facts.to_string(), recorded.to_string());
+
+        let json = serde_json::to_value(&facts).unwrap();
+        assert_eq!(SCHEMA_VERSION, json["version"]);
+        assert_eq!("exists", json["state"]["type"]);
+        assert_eq!("file", json["state"]["resolved_type"]);
+    }
+
+    #[test]
+    fn test_round_trip_parent_problem() {
+        let tempdir = tempfile::tempdir().unwrap();
+        std::fs::write(tempdir.path().join("a"), "").unwrap();
+
+        let facts = PathFacts::new(tempdir.path().join("a").join("b").join("c.txt"));
+        let recorded = round_trip(&facts);
+        assert_eq!(facts.to_string(), recorded.to_string());
+
+        let error = recorded.error().unwrap();
+        assert_eq!(ErrorKind::NotADirectory, error.kind());
+        assert_eq!(facts.error().unwrap().to_string(), error.to_string());
+
+        let json = serde_json::to_value(&recorded).unwrap();
+        assert_eq!("NotADirectory", json["state"]["error"]["kind"]);
+        assert_eq!(
+            facts.error().unwrap().raw_os_error(),
+            json["state"]["error"]["raw_os_error"]
+                .as_i64()
+                .map(|errno| errno as i32)
+        );
+    }
+
+    #[test]
+    fn test_round_trip_operation() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let facts = PathFacts::for_operation(tempdir.path(), crate::Operation::CreateDir);
+        let recorded = round_trip(&facts);
+        assert_eq!(facts.to_string(), recorded.to_string());
+        assert_eq!(&[Blocker::AlreadyExists], recorded.blockers());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let result = serde_json::from_str::<PathFacts>(
+            r#"{"version":999,"path":"","state":{"type":"empty_path"}}"#,
+        );
+        let error = result.err().unwrap();
+        assert!(error
+            .to_string()
+            .contains("unsupported path_facts schema version 999"));
+    }
+}
@@ -1,24 +1,57 @@
-use crate::{abs_path::AbsPath, happy_path::DirOk};
+use crate::{abs_path::AbsPath, happy_path::DirOk, permissions::Permissions};
 use std::path::Path;
 
 pub(crate) fn bullet(contents: impl AsRef<str>) -> String {
     prefix_first_rest_lines(" - ", "   ", contents.as_ref())
 }
 
-/// Shows permissions if any are disabled
-pub(crate) fn conditional_perms(read: bool, write: bool, execute: bool) -> String {
-    if read && write && execute {
+/// Shows every permission
+pub(crate) fn perms(permissions: &Permissions) -> String {
+    [
+        (permissions.read, "read"),
+        (permissions.write, "write"),
+        (permissions.execute, "execute"),
+    ]
+    .iter()
+    .map(|(allowed, name)| {
+        if *allowed {
+            format!("✅ {name}")
+        } else {
+            format!("❌ {name}")
+        }
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Shows relevant permissions if any of them are disabled
+///
+/// Pass `Permissions::ALL` to consider every permission relevant. Used to de-emphasize
+/// permissions that an operation does not need.
+pub(crate) fn conditional_perms(permissions: &Permissions, relevant: &Permissions) -> String {
+    let checks = [
+        (relevant.read, permissions.read, "read"),
+        (relevant.write, permissions.write, "write"),
+        (relevant.execute, permissions.execute, "execute"),
+    ];
+    if checks
+        .iter()
+        .all(|(relevant, allowed, _)| !relevant || *allowed)
+    {
         "".to_string()
     } else {
-        let mut perms = vec![];
-        perms.push(if read { "✅ read" } else { "❌ read" });
-        perms.push(if write { "✅ write" } else { "❌ write" });
-        perms.push(if execute {
-            "✅ execute"
-        } else {
-            "❌ execute"
-        });
-        perms.join(", ")
+        checks
+            .iter()
+            .filter(|(relevant, _, _)| *relevant)
+            .map(|(_, allowed, name)| {
+                if *allowed {
+                    format!("✅ {name}")
+                } else {
+                    format!("❌ {name}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 }
 
@@ -76,13 +109,14 @@ pub(crate) fn append_if(append: impl AsRef<str>, contents: impl AsRef<str>) -> S
     }
 }
 
-pub(crate) fn fmt_dir<F>(dir: &DirOk, annotate: F) -> String
+/// Formats a directory and its entries, showing relevant permissions of the directory itself
+pub(crate) fn fmt_dir<F>(dir: &DirOk, relevant: &Permissions, annotate: F) -> String
 where
     F: Fn(&AbsPath) -> Option<String>,
 {
     let entries = &dir.entries;
     let mut out = String::new();
-    let permissions = append_if(" ", conditional_perms(dir.read, dir.write, dir.execute));
+    let permissions = append_if(" ", conditional_perms(&dir.permissions, relevant));
     out.push_str(&format!("{path}{permissions}\n", path = dir.absolute));
     out.push_str(&fmt_dir_entries_annotate(entries, annotate));
     out
@@ -124,3 +158,73 @@ pub(crate) fn filename(path: &Path) -> Option<std::path::Display<'_>> {
 pub(crate) fn filename_or_path(path: &Path) -> std::path::Display<'_> {
     filename(path).unwrap_or_else(|| path.display())
 }
+
+/// Formats unix mode bits like `ls -l` does, such as `drwxr-xr-x`
+///
+/// Includes the file type character when the type bits are set, and the setuid, setgid, and
+/// sticky bits as `s`/`S` and `t`/`T`.
+pub(crate) fn mode_string(mode: u32) -> String {
+    // POSIX `S_IFMT` file type bits
+    let file_type = match mode & 0o170000 {
+        0o040000 => "d",
+        0o120000 => "l",
+        0o100000 => "-",
+        0o010000 => "p",
+        0o140000 => "s",
+        0o060000 => "b",
+        0o020000 => "c",
+        _ => "",
+    };
+    let triplet = |shift: u32, special: bool, set: char, unset: char| {
+        let bits = (mode >> shift) & 0o7;
+        let execute = match (bits & 0o1 != 0, special) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        };
+        format!(
+            "{}{}{execute}",
+            if bits & 0o4 != 0 { 'r' } else { '-' },
+            if bits & 0o2 != 0 { 'w' } else { '-' },
+        )
+    };
+    format!(
+        "{file_type}{user}{group}{other}",
+        user = triplet(6, mode & 0o4000 != 0, 's', 'S'),
+        group = triplet(3, mode & 0o2000 != 0, 's', 'S'),
+        other = triplet(0, mode & 0o1000 != 0, 't', 'T'),
+    )
+}
+
+/// Formats a byte count with decimal units like `df -H` does, such as `12.3 GB`
+pub(crate) fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["kB", "MB", "GB", "TB", "PB", "EB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_mode_string() {
+        assert_eq!("drwxr-xr-x", mode_string(0o040755));
+        assert_eq!("-rwsr-Sr-T", mode_string(0o107744));
+        assert_eq!("rw-rw-rwt", mode_string(0o1667));
+    }
+}
@@ -0,0 +1,305 @@
+//! Follows a chain of symlinks one hop at a time
+//!
+//! Following a broken symlink to completion isn't possible and a loop of symlinks never
+//! completes. Instead of canonicalizing in one step (which only tells us that something
+//! went wrong) each link is read individually, up to a fixed number of hops, so we can
+//! name the exact hop where the chain breaks.
+use crate::{abs_path::AbsPath, style};
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Same limit as Linux `MAXSYMLINKS`, after which resolving a path fails with `ELOOP`
+pub(crate) const MAX_HOPS: usize = 40;
+
+/// Every symlink followed from a path to its final target, shown when `Display`ed.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymlinkChain {
+    start: PathBuf,
+    hops: Vec<SymlinkHop>,
+    end: SymlinkEnd,
+}
+
+/// A single link in a `SymlinkChain`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymlinkHop {
+    link: PathBuf,
+    raw_target: PathBuf,
+    target: PathBuf,
+    target_exists: bool,
+}
+
+/// How a `SymlinkChain` ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum SymlinkEnd {
+    /// The last target exists and is not a symlink
+    Resolved,
+    /// The last target does not exist
+    Broken,
+    /// The last target was already visited, or resolving it reported `ELOOP`
+    Loop,
+    /// Stopped after following the maximum number of hops
+    HopLimit,
+    /// A link or target could not be read
+    Unreadable {
+        #[cfg_attr(feature = "serde", serde(with = "crate::schema::error_kind"))]
+        kind: ErrorKind,
+    },
+}
+
+impl SymlinkChain {
+    /// Follows symlinks starting at the given path
+    ///
+    /// When the path is not a symlink the chain has no hops.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let start = path.as_ref().to_path_buf();
+        let (hops, end) = match AbsPath::new(&start) {
+            Ok(absolute) => walk(absolute.as_ref()),
+            Err(_) => (
+                vec![],
+                SymlinkEnd::Unreadable {
+                    kind: ErrorKind::InvalidInput,
+                },
+            ),
+        };
+        SymlinkChain { start, hops, end }
+    }
+
+    /// The path the chain starts at, as it was given
+    pub fn start(&self) -> &Path {
+        &self.start
+    }
+
+    /// Every followed link, in order
+    pub fn hops(&self) -> &[SymlinkHop] {
+        &self.hops
+    }
+
+    /// How the chain ended
+    pub fn end(&self) -> SymlinkEnd {
+        self.end
+    }
+
+    /// The final target when the chain resolved
+    pub fn resolved(&self) -> Option<&Path> {
+        match self.end {
+            SymlinkEnd::Resolved => self.hops.last().map(|hop| hop.target.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// The hop where the chain breaks, when it does not resolve
+    pub fn broken_at(&self) -> Option<&SymlinkHop> {
+        match self.end {
+            SymlinkEnd::Resolved => None,
+            _ => self.hops.last(),
+        }
+    }
+}
+
+impl SymlinkHop {
+    /// Absolute path of the symlink
+    pub fn link(&self) -> &Path {
+        &self.link
+    }
+
+    /// Target exactly as it is stored in the link, may be relative
+    pub fn raw_target(&self) -> &Path {
+        &self.raw_target
+    }
+
+    /// Target made absolute, relative targets are relative to the directory of the link
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Whether the target exists (without following it if it's a symlink)
+    pub fn target_exists(&self) -> bool {
+        self.target_exists
+    }
+}
+
+/// Relative targets are relative to the directory containing the link, not the link itself
+pub(crate) fn resolve_target(link: &Path, raw_target: &Path) -> PathBuf {
+    match link.parent() {
+        Some(dir) => dir.join(raw_target),
+        None => raw_target.to_path_buf(),
+    }
+}
+
+fn walk(start: &Path) -> (Vec<SymlinkHop>, SymlinkEnd) {
+    let mut hops: Vec<SymlinkHop> = vec![];
+    let mut current = start.to_path_buf();
+    loop {
+        match std::fs::symlink_metadata(&current) {
+            Ok(metadata) if !metadata.is_symlink() => return (hops, SymlinkEnd::Resolved),
+            Ok(_) => {}
+            Err(error) => return (hops, end_for(&error)),
+        }
+        if hops.len() == MAX_HOPS {
+            return (hops, SymlinkEnd::HopLimit);
+        }
+        let raw_target = match std::fs::read_link(&current) {
+            Ok(raw_target) => raw_target,
+            Err(error) => return (hops, SymlinkEnd::Unreadable { kind: error.kind() }),
+        };
+        let target = resolve_target(&current, &raw_target);
+        let seen = target == start || hops.iter().any(|hop| hop.link == target);
+        let lookup = std::fs::symlink_metadata(&target);
+        hops.push(SymlinkHop {
+            link: current,
+            raw_target,
+            target: target.clone(),
+            target_exists: lookup.is_ok(),
+        });
+        match lookup {
+            _ if seen => return (hops, SymlinkEnd::Loop),
+            Ok(metadata) if metadata.is_symlink() => current = target,
+            Ok(_) => return (hops, SymlinkEnd::Resolved),
+            Err(error) => return (hops, end_for(&error)),
+        }
+    }
+}
+
+fn end_for(error: &std::io::Error) -> SymlinkEnd {
+    if is_loop(error) {
+        SymlinkEnd::Loop
+    } else if error.kind() == ErrorKind::NotFound {
+        SymlinkEnd::Broken
+    } else {
+        SymlinkEnd::Unreadable { kind: error.kind() }
+    }
+}
+
+#[cfg(unix)]
+fn is_loop(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ELOOP)
+}
+
+#[cfg(not(unix))]
+fn is_loop(_error: &std::io::Error) -> bool {
+    false
+}
+
+impl Display for SymlinkHop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` -> `{}`",
+            self.link.display(),
+            self.raw_target.display()
+        )?;
+        if self.raw_target != self.target {
+            write!(f, " (`{}`)", self.target.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SymlinkEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymlinkEnd::Resolved => write!(f, "resolved"),
+            SymlinkEnd::Broken => write!(f, "target does not exist"),
+            SymlinkEnd::Loop => write!(f, "symlink loop"),
+            SymlinkEnd::HopLimit => write!(f, "stopped after {MAX_HOPS} hops"),
+            SymlinkEnd::Unreadable { kind } => write!(f, "cannot read link: {kind}"),
+        }
+    }
+}
+
+impl SymlinkChain {
+    /// A bullet for every hop, highlighting the hop where the chain breaks
+    pub(crate) fn fmt_hops(&self) -> String {
+        if self.hops.is_empty() {
+            return style::bullet(format!("Not followed: {}", self.end)) + "\n";
+        }
+        let last = self.hops.len() - 1;
+        self.hops
+            .iter()
+            .enumerate()
+            .map(|(index, hop)| match self.end {
+                SymlinkEnd::Resolved => style::bullet(hop.to_string()),
+                end if index == last => style::bullet(format!("{hop} <- chain breaks here: {end}")),
+                _ => style::bullet(hop.to_string()),
+            })
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+impl Display for SymlinkChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "symlink chain of `{}`:", self.start.display())?;
+        write!(f, "{}", self.fmt_hops())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_relative_hops_resolve() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("dir");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(tempdir.path().join("file.txt"), "").unwrap();
+        symlink("../file.txt", dir.join("first")).unwrap();
+        symlink("dir/first", tempdir.path().join("second")).unwrap();
+
+        let chain = SymlinkChain::new(tempdir.path().join("second"));
+        assert_eq!(SymlinkEnd::Resolved, chain.end());
+        assert_eq!(2, chain.hops().len());
+        assert_eq!(Path::new("dir/first"), chain.hops()[0].raw_target());
+        assert_eq!(dir.join("first"), chain.hops()[0].target());
+        assert_eq!(dir.join("../file.txt"), chain.hops()[1].target());
+        assert!(chain.broken_at().is_none());
+    }
+
+    #[test]
+    fn test_broken_hop() {
+        let tempdir = tempfile::tempdir().unwrap();
+        symlink("missing.txt", tempdir.path().join("broken")).unwrap();
+        symlink("broken", tempdir.path().join("link")).unwrap();
+
+        let chain = SymlinkChain::new(tempdir.path().join("link"));
+        assert_eq!(SymlinkEnd::Broken, chain.end());
+        let hop = chain.broken_at().unwrap();
+        assert_eq!(tempdir.path().join("broken"), hop.link());
+        assert!(!hop.target_exists());
+        assert!(chain.to_string().contains(&format!(
+            " - `{}` -> `missing.txt` (`{}`) <- chain breaks here: target does not exist\n",
+            tempdir.path().join("broken").display(),
+            tempdir.path().join("missing.txt").display()
+        )));
+    }
+
+    #[test]
+    fn test_loop() {
+        let tempdir = tempfile::tempdir().unwrap();
+        symlink("b", tempdir.path().join("a")).unwrap();
+        symlink("a", tempdir.path().join("b")).unwrap();
+
+        let chain = SymlinkChain::new(tempdir.path().join("a"));
+        assert_eq!(SymlinkEnd::Loop, chain.end());
+        assert_eq!(2, chain.hops().len());
+        assert_eq!(
+            tempdir.path().join("a"),
+            chain.broken_at().unwrap().target()
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Permissions of a path for a user other than the current process
+//!
+//! `Permissions::effective` asks the kernel about the current process. A layout prepared by
+//! one user is often used by another, such as a build step running as `root` and the app
+//! running as `heroku`. Access for another identity is computed from the owner, group, and
+//! mode bits, and POSIX ACLs when present, so it can be checked before switching users.
+//!
+//! Computed access does not know about read-only mounts, immutable files, or security
+//! modules such as SELinux.
+use crate::{abs_path::AbsPath, acl, ownership, permissions::Permissions, style};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// Computed permissions of every component of a path for a user, from the root down
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserAccess {
+    pub(crate) uid: u32,
+    pub(crate) gids: Vec<u32>,
+    pub(crate) user: Option<String>,
+    pub(crate) components: Vec<ComponentAccess>,
+}
+
+/// Computed permissions of a single component for a user
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComponentAccess {
+    pub(crate) path: PathBuf,
+    pub(crate) exists: bool,
+    pub(crate) is_dir: bool,
+    /// Permissions from the mode bits and ACL of this component alone
+    pub(crate) permissions: Option<Permissions>,
+    /// Whether an ACL was used instead of the mode bits
+    pub(crate) acl: bool,
+}
+
+impl UserAccess {
+    /// Computes access for a uid and its groups, primary group first
+    ///
+    /// Stops after the first component that does not exist or cannot be inspected.
+    pub(crate) fn new(path: &Path, uid: u32, gids: &[u32]) -> Self {
+        let mut components = vec![];
+        if let Ok(absolute) = AbsPath::new(path) {
+            for component in absolute.root_down() {
+                let access = ComponentAccess::new(component.as_ref(), uid, gids);
+                let stop = access.permissions.is_none();
+                components.push(access);
+                if stop {
+                    break;
+                }
+            }
+        }
+
+        UserAccess {
+            uid,
+            gids: gids.to_vec(),
+            user: ownership::name_for_id("/etc/passwd", uid),
+            components,
+        }
+    }
+
+    /// The uid access was computed for
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The groups access was computed for
+    pub fn gids(&self) -> &[u32] {
+        &self.gids
+    }
+
+    /// Name of the user from `/etc/passwd`, if listed
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Every inspected component, from the root down
+    pub fn components(&self) -> &[ComponentAccess] {
+        &self.components
+    }
+
+    /// The first directory above the target that the user cannot search
+    pub fn blocked_by(&self) -> Option<&ComponentAccess> {
+        let (_, ancestors) = self.components.split_last()?;
+        ancestors
+            .iter()
+            .find(|component| component.blocks_traversal())
+    }
+
+    /// Permissions of the target for the user, including traversal of every ancestor
+    ///
+    /// `None` when the target or one of its ancestors does not exist or cannot be inspected.
+    pub fn permissions(&self) -> Option<Permissions> {
+        // Components stop at the first error, so the last one is the target when it has permissions
+        let permissions = self.components.last()?.permissions?;
+        if self.blocked_by().is_some() {
+            Some(Permissions::new(false, false, false))
+        } else {
+            Some(permissions)
+        }
+    }
+}
+
+impl ComponentAccess {
+    #[cfg(unix)]
+    pub(crate) fn new(path: &Path, uid: u32, gids: &[u32]) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        let mut access = ComponentAccess {
+            path: path.to_path_buf(),
+            exists: true,
+            is_dir: false,
+            permissions: None,
+            acl: false,
+        };
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                access.exists = error.kind() != std::io::ErrorKind::NotFound;
+                return access;
+            }
+        };
+        let acl = acl::Acl::read(path, acl::ACCESS);
+        access.is_dir = metadata.is_dir();
+        access.acl = acl.is_some();
+        access.permissions = Some(compute(
+            metadata.uid(),
+            metadata.gid(),
+            metadata.mode(),
+            metadata.is_dir(),
+            acl.as_ref(),
+            uid,
+            gids,
+        ));
+        access
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new(path: &Path, _uid: u32, _gids: &[u32]) -> Self {
+        ComponentAccess {
+            path: path.to_path_buf(),
+            exists: path.exists(),
+            is_dir: path.is_dir(),
+            permissions: None,
+            acl: false,
+        }
+    }
+
+    /// Absolute path of the component
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// False when the component was not found
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+
+    /// Permissions of this component alone, `None` when it could not be inspected
+    pub fn permissions(&self) -> Option<Permissions> {
+        self.permissions
+    }
+
+    /// True when an ACL decided the permissions instead of the mode bits
+    pub fn has_acl(&self) -> bool {
+        self.acl
+    }
+
+    /// True when the user cannot search through this component to reach paths below it
+    pub fn blocks_traversal(&self) -> bool {
+        match self.permissions {
+            Some(permissions) => !self.is_dir || !permissions.execute,
+            None => true,
+        }
+    }
+}
+
+/// Computes permissions from ownership, mode, and ACL the way the kernel checks them
+fn compute(
+    owner: u32,
+    group: u32,
+    mode: u32,
+    is_dir: bool,
+    acl: Option<&acl::Acl>,
+    uid: u32,
+    gids: &[u32],
+) -> Permissions {
+    if uid == 0 {
+        return Permissions::for_root(mode, is_dir);
+    }
+    let bits = match acl {
+        Some(acl) => acl.access_bits(owner, group, uid, gids),
+        None if uid == owner => (mode >> 6) & 0o7,
+        None if gids.contains(&group) => (mode >> 3) & 0o7,
+        None => mode & 0o7,
+    };
+    Permissions::new(bits & 0o4 != 0, bits & 0o2 != 0, bits & 0o1 != 0)
+}
+
+impl Display for ComponentAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.display();
+        match self.permissions {
+            Some(permissions) => {
+                write!(f, "`{path}` ({})", style::perms(&permissions))?;
+                if self.acl {
+                    write!(f, " from ACL")?;
+                }
+                Ok(())
+            }
+            None if !self.exists => write!(f, "`{path}` does not exist"),
+            None => write!(f, "`{path}` cannot be inspected"),
+        }
+    }
+}
+
+impl Display for UserAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let user = match self.user() {
+            Some(name) => format!("uid {} `{name}`", self.uid),
+            None => format!("uid {}", self.uid),
+        };
+        let gids = self
+            .gids
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            f,
+            "Permissions for {user} (groups: {gids}) from the root down:"
+        )?;
+        let blocked = self.blocked_by().map(|component| &component.path);
+        for component in &self.components {
+            if Some(&component.path) == blocked {
+                let reason = if component.is_dir || component.permissions.is_none() {
+                    "cannot be searched by this user"
+                } else {
+                    "not a directory"
+                };
+                writeln!(
+                    f,
+                    "{}",
+                    style::bullet(format!("{component} <- breaks traversal: {reason}"))
+                )?;
+            } else {
+                writeln!(f, "{}", style::bullet(component.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_compute_mode_bits() {
+        let perms = |p: Permissions| (p.read, p.write, p.execute);
+        assert_eq!(
+            (true, true, false),
+            perms(compute(1000, 100, 0o640, false, None, 1000, &[]))
+        );
+        assert_eq!(
+            (true, false, false),
+            perms(compute(1000, 100, 0o640, false, None, 1002, &[1002, 100]))
+        );
+        assert_eq!(
+            (false, false, false),
+            perms(compute(1000, 100, 0o640, false, None, 1002, &[1002]))
+        );
+        // Owner bits apply even when they are stricter than the group bits
+        assert_eq!(
+            (false, false, false),
+            perms(compute(1000, 100, 0o070, true, None, 1000, &[100]))
+        );
+        assert_eq!(
+            (true, true, true),
+            perms(compute(1000, 100, 0o000, true, None, 0, &[0]))
+        );
+        assert_eq!(
+            (true, true, false),
+            perms(compute(1000, 100, 0o600, false, None, 0, &[0]))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_blocked_by_private_dir() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path().join("private");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let owner = std::fs::metadata(&file).unwrap().uid();
+        std::fs::set_permissions(tempdir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let other = owner + 1002;
+        let access = UserAccess::new(&file, other, &[other]);
+        let last = access.components().last().unwrap();
+        assert_eq!(file, last.path());
+        assert_eq!(
+            Some(Permissions::new(true, false, false)),
+            last.permissions()
+        );
+        assert_eq!(
+            Some(dir.as_path()),
+            access.blocked_by().map(ComponentAccess::path)
+        );
+        assert_eq!(
+            Some(Permissions::new(false, false, false)),
+            access.permissions()
+        );
+        assert!(access.to_string().contains(&format!(
+            " - `{}` (❌ read, ❌ write, ❌ execute) <- breaks traversal: cannot be searched by this user\n",
+            dir.display()
+        )));
+
+        let access = UserAccess::new(&file, owner, &[]);
+        assert_eq!(None, access.blocked_by());
+        assert_eq!(
+            Some(Permissions::new(true, true, false)),
+            access.permissions()
+        );
+
+        let missing = UserAccess::new(&dir.join("missing"), other, &[other]);
+        assert!(!missing.components().last().unwrap().exists());
+        assert_eq!(None, missing.permissions());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_acl_grants_named_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let file = tempdir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        // user::rw- user:1002:rw- group::r-- mask::rw- other::---
+        let bytes = acl::tests::encode(&[
+            (0x01, 0o6, u32::MAX),
+            (0x02, 0o6, 1002),
+            (0x04, 0o4, u32::MAX),
+            (0x10, 0o6, u32::MAX),
+            (0x20, 0o0, u32::MAX),
+        ]);
+        if !crate::xattr::set(&file, acl::ACCESS, &bytes) {
+            // Filesystem without ACL support
+            return;
+        }
+
+        let access = ComponentAccess::new(&file, 1002, &[1002]);
+        assert!(access.has_acl());
+        assert_eq!(
+            Some(Permissions::new(true, true, false)),
+            access.permissions()
+        );
+        assert_eq!(
+            format!(
+                "`{}` (✅ read, ✅ write, ❌ execute) from ACL",
+                file.display()
+            ),
+            access.to_string()
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Reads extended attributes
+//!
+//! Extended attributes hold metadata that mode bits don't show, such as POSIX ACLs in
+//! `system.posix_acl_access` and SELinux labels in `security.selinux`. Only Linux is
+//! supported, other platforms report no attributes.
+use std::path::Path;
+
+/// Reads an extended attribute, following symlinks
+///
+/// Returns `Ok(None)` when the attribute does not exist or the filesystem does not support
+/// extended attributes.
+#[cfg(target_os = "linux")]
+pub(crate) fn get(path: &Path, name: &str) -> Result<Option<Vec<u8>>, std::io::Error> {
+    let path = c_string(path.as_os_str())?;
+    let name = c_string(name.as_ref())?;
+    let result = read_sized(|buffer, size| {
+        // SAFETY: Both strings are nul terminated and `buffer` has room for `size` bytes
+        unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buffer.cast(), size) }
+    });
+    match result {
+        Err(error) if is_absent(&error) => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn get(_path: &Path, _name: &str) -> Result<Option<Vec<u8>>, std::io::Error> {
+    Ok(None)
+}
+
+/// Names and value sizes of every extended attribute the process can see, following symlinks
+///
+/// Returns an empty list when the filesystem does not support extended attributes.
+#[cfg(target_os = "linux")]
+pub(crate) fn list(path: &Path) -> Result<Vec<(String, usize)>, std::io::Error> {
+    let c_path = c_string(path.as_os_str())?;
+    let names = match read_sized(|buffer, size| {
+        // SAFETY: `c_path` is nul terminated and `buffer` has room for `size` bytes
+        unsafe { libc::listxattr(c_path.as_ptr(), buffer.cast(), size) }
+    }) {
+        Ok(names) => names,
+        Err(error) if is_absent(&error) => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+    Ok(names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let name = String::from_utf8_lossy(name).into_owned();
+            // Attributes can disappear between listing and reading them
+            let size = get(path, &name)
+                .ok()
+                .flatten()
+                .map_or(0, |value| value.len());
+            (name, size)
+        })
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn list(_path: &Path) -> Result<Vec<(String, usize)>, std::io::Error> {
+    Ok(vec![])
+}
+
+#[cfg(target_os = "linux")]
+fn c_string(value: &std::ffi::OsStr) -> Result<std::ffi::CString, std::io::Error> {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::CString::new(value.as_bytes())
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))
+}
+
+#[cfg(target_os = "linux")]
+fn is_absent(error: &std::io::Error) -> bool {
+    matches!(error.raw_os_error(), Some(libc::ENODATA | libc::ENOTSUP))
+}
+
+/// Calls a `getxattr` style function once for the size and again for the value
+///
+/// Retries when the value grows between the two calls.
+#[cfg(target_os = "linux")]
+fn read_sized<F>(call: F) -> Result<Vec<u8>, std::io::Error>
+where
+    F: Fn(*mut u8, usize) -> libc::ssize_t,
+{
+    loop {
+        // A size of zero only returns the length
+        let len = call(std::ptr::null_mut(), 0);
+        if len < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; len as usize];
+        let read = call(buffer.as_mut_ptr(), buffer.len());
+        if read >= 0 {
+            buffer.truncate(read as usize);
+            return Ok(buffer);
+        }
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ERANGE) {
+            return Err(error);
+        }
+    }
+}
+
+/// Sets an extended attribute, returns false when the filesystem does not support it
+#[cfg(all(test, target_os = "linux"))]
+pub(crate) fn set(path: &Path, name: &str, value: &[u8]) -> bool {
+    let path = c_string(path.as_os_str()).unwrap();
+    let name = c_string(name.as_ref()).unwrap();
+    // SAFETY: Both strings are nul terminated and `value` outlives the call
+    unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        ) == 0
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_get_and_list() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("file.txt");
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(None, get(&path, "user.path_facts").unwrap());
+        if !set(&path, "user.path_facts", b"fact") {
+            // Filesystem without user xattr support
+            return;
+        }
+
+        assert_eq!(
+            Some(b"fact".to_vec()),
+            get(&path, "user.path_facts").unwrap()
+        );
+        assert!(list(&path)
+            .unwrap()
+            .contains(&("user.path_facts".to_string(), 4)));
+        assert!(get(&tempdir.path().join("missing"), "user.path_facts").is_err());
+    }
+}